@@ -0,0 +1,30 @@
+use crate::models::general::*;
+use crate::models::cache::*;
+use crate::utils::cache::{EmbeddingCache, EmbeddingCacheStats};
+use actix_web::*;
+
+
+impl Cache {
+	pub async fn stats() -> impl Responder {
+        let stats = match EmbeddingCache::global() {
+            Some(cache) => cache.stats(),
+            None => EmbeddingCacheStats::disabled(),
+        };
+
+        HttpResponse::Ok().json(GeneralValueResult{result: stats, status: true})
+	}
+
+	pub async fn clear() -> impl Responder {
+        let cache = match EmbeddingCache::global() {
+            Some(cache) => cache,
+            None => {
+                return HttpResponse::Ok().json(GeneralValueResult{result: EmbeddingCacheStats::disabled(), status: true});
+            }
+        };
+
+        match cache.clear() {
+            Ok(_) => HttpResponse::Ok().json(GeneralValueResult{result: cache.stats(), status: true}),
+            Err(e) => HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)}),
+        }
+	}
+}
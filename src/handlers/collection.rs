@@ -0,0 +1,649 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::models::general::*;
+use crate::models::collection::*;
+use crate::models::database::*;
+use crate::handlers::database::{DEFAULT_EMBEDDING_MODEL, DEFAULT_PROMPT_MODEL};
+use crate::utils::ollama::Ollama;
+use crate::utils::ids::IdStrategy;
+use crate::utils::bm25::SPARSE_VECTOR_NAME;
+use actix_web::*;
+use actix_web::web;
+
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use qdrant_client::Qdrant;
+use qdrant_client::qdrant::{
+    CollectionInfo,
+    CollectionStatus,
+    CreateCollectionBuilder,
+    CreateFieldIndexCollectionBuilder,
+    DeletePointsBuilder,
+    Distance,
+    FieldType,
+    GetPointsBuilder,
+    HnswConfigDiffBuilder,
+    Modifier,
+    PayloadSchemaType,
+    PointId,
+    PointStruct,
+    PointsIdsList,
+    SparseVectorParamsBuilder,
+    SparseVectorsConfigBuilder,
+    TextIndexParamsBuilder,
+    TokenizerType,
+    UpsertPointsBuilder,
+    VectorParamsBuilder,
+    vectors_config::Config,
+};
+
+
+/// Internal collection holding one `CollectionMeta` point per user collection.
+pub const META_COLLECTION: &str = "naomi_db_meta";
+
+/// Payload field carrying the full-text index used by keyword search.
+pub const TEXT_INDEX_FIELD: &str = "original_document";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CollectionMeta {
+    pub collection: String,
+    pub model: Option<String>,
+    pub translate_to: Option<String>,
+    pub prompt_model: Option<String>,
+    pub id_strategy: Option<IdStrategy>,
+    pub id_namespace_field: Option<String>,
+    pub dimension: u64,
+    pub created_at: NaiveDateTime,
+}
+
+/// What a collection's vectors support, as read from Qdrant.
+#[derive(Clone, Copy)]
+pub struct VectorConfig {
+    pub bm25: bool,
+    pub distance: Distance,
+    pub size: Option<u64>,
+}
+
+impl VectorConfig {
+    /// Cosine and dot scores grow with similarity; euclid and manhattan scores are distances.
+    pub fn is_similarity(&self) -> bool {
+        !matches!(self.distance, Distance::Euclid | Distance::Manhattan)
+    }
+
+    pub fn distance_of(&self, score: f32) -> f32 {
+        if self.is_similarity() { 1.0 - score } else { score }
+    }
+
+    /// Turns the cutoffs into Qdrant's `score_threshold`, which is a lower bound on
+    /// similarity scores and an upper bound on distance scores.
+    pub fn score_threshold(&self, min_score: Option<f32>, max_distance: Option<f32>) -> Result<Option<f32>, String> {
+        if !self.is_similarity() {
+            if min_score.is_some() {
+                let name = self.distance.as_str_name().to_lowercase();
+                return Err(format!("min_score does not apply to {} distance; use max_distance", name));
+            }
+            return Ok(max_distance);
+        }
+
+        let from_distance = max_distance.map(|d| 1.0 - d);
+        Ok(match (min_score, from_distance) {
+            (Some(score), Some(bound)) => Some(score.max(bound)),
+            (score, bound) => score.or(bound),
+        })
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CollectionHnswReq {
+    pub m: Option<u64>,
+    pub ef_construct: Option<u64>,
+    pub full_scan_threshold: Option<u64>,
+    pub on_disk: Option<bool>,
+}
+
+/// Options of the full-text index on `original_document`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TextIndexReq {
+    /// word (default), whitespace, prefix or multilingual
+    pub tokenizer: Option<String>,
+    /// Lowercases tokens so matching ignores case; on by default
+    pub lowercase: Option<bool>,
+    pub min_token_len: Option<u64>,
+    pub max_token_len: Option<u64>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct PostCollectionReq {
+    pub name: String,
+    pub model: Option<String>,
+    pub translate_to: Option<String>,
+    pub id_strategy: Option<IdStrategy>,
+    pub id_namespace_field: Option<String>,
+    pub size: Option<u64>,
+    pub distance: Option<String>,
+    pub on_disk: Option<bool>,
+    pub hnsw: Option<CollectionHnswReq>,
+    /// Adds the BM25 sparse vector used by hybrid search; on by default
+    pub bm25: Option<bool>,
+    /// Creates the full-text index used by keyword search
+    pub text_index: Option<TextIndexReq>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CollectionIndexResult {
+    pub data_type: String,
+    pub points: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CollectionInfoResult {
+    pub name: String,
+    pub status: String,
+    pub points_count: Option<u64>,
+    pub indexed_vectors_count: Option<u64>,
+    pub segments_count: u64,
+    pub vector_size: Option<u64>,
+    pub distance: Option<String>,
+    pub sparse_vectors: Vec<String>,
+    pub payload_indexes: Map<String, Value>,
+    pub meta: Option<CollectionMeta>,
+}
+
+impl Collection {
+
+    pub fn parse_distance(name: &str) -> Option<Distance> {
+        match name.to_lowercase().as_str() {
+            "cosine" => Some(Distance::Cosine),
+            "euclid" | "euclidean" => Some(Distance::Euclid),
+            "dot" => Some(Distance::Dot),
+            "manhattan" => Some(Distance::Manhattan),
+            _ => None,
+        }
+    }
+
+    pub fn parse_tokenizer(name: &str) -> Option<TokenizerType> {
+        match name.to_lowercase().as_str() {
+            "word" => Some(TokenizerType::Word),
+            "whitespace" => Some(TokenizerType::Whitespace),
+            "prefix" => Some(TokenizerType::Prefix),
+            "multilingual" => Some(TokenizerType::Multilingual),
+            _ => None,
+        }
+    }
+
+    pub fn validate_text_index(req: &TextIndexReq) -> Result<(), String> {
+        if let Some(name) = &req.tokenizer {
+            if Collection::parse_tokenizer(name).is_none() {
+                return Err(format!("Unknown tokenizer '{}'", name));
+            }
+        }
+        if let (Some(min), Some(max)) = (req.min_token_len, req.max_token_len) {
+            if min > max {
+                return Err("min_token_len may not exceed max_token_len".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds (or rebuilds) the full-text index on `original_document`.
+    pub async fn create_text_index(client: &Qdrant, collection_name: &str, req: &TextIndexReq) -> Result<(), String> {
+        Collection::validate_text_index(req)?;
+        let tokenizer = req.tokenizer.as_deref()
+            .and_then(Collection::parse_tokenizer)
+            .unwrap_or(TokenizerType::Word);
+
+        let mut params = TextIndexParamsBuilder::new(tokenizer).lowercase(req.lowercase.unwrap_or(true));
+        if let Some(min_token_len) = req.min_token_len {
+            params = params.min_token_len(min_token_len);
+        }
+        if let Some(max_token_len) = req.max_token_len {
+            params = params.max_token_len(max_token_len);
+        }
+
+        let request = CreateFieldIndexCollectionBuilder::new(collection_name, TEXT_INDEX_FIELD, FieldType::Text)
+            .field_index_params(params)
+            .wait(true);
+        match client.create_field_index(request).await {
+            Ok(_) => {
+                println!("Text index on '{}' created.", collection_name);
+                Ok(())
+            },
+            Err(e) => {
+                println!("{}", e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    pub async fn has_text_index(client: &Qdrant, collection_name: &str) -> Result<bool, String> {
+        let info = client.collection_info(collection_name).await.map_err(|e| e.to_string())?;
+        Ok(info.result
+            .and_then(|i| i.payload_schema.get(TEXT_INDEX_FIELD).cloned())
+            .map(|schema| schema.data_type == PayloadSchemaType::Text as i32)
+            .unwrap_or(false))
+    }
+
+    pub async fn create_collection(client: &Qdrant, req: &PostCollectionReq, size: u64) -> Result<(), String> {
+        let distance = match &req.distance {
+            Some(name) => match Collection::parse_distance(name) {
+                Some(d) => d,
+                None => return Err(format!("Unknown distance '{}'", name)),
+            },
+            None => Distance::Cosine,
+        };
+
+        let mut vector_params = VectorParamsBuilder::new(size, distance);
+        if let Some(on_disk) = req.on_disk {
+            vector_params = vector_params.on_disk(on_disk);
+        }
+
+        let mut builder = CreateCollectionBuilder::new(req.name.clone()).vectors_config(vector_params);
+        if req.bm25.unwrap_or(true) {
+            let mut sparse_config = SparseVectorsConfigBuilder::default();
+            sparse_config.add_named_vector_params(SPARSE_VECTOR_NAME, SparseVectorParamsBuilder::default().modifier(Modifier::Idf));
+            builder = builder.sparse_vectors_config(sparse_config);
+        }
+        if let Some(hnsw) = &req.hnsw {
+            let mut hnsw_config = HnswConfigDiffBuilder::default();
+            if let Some(m) = hnsw.m {
+                hnsw_config = hnsw_config.m(m);
+            }
+            if let Some(ef_construct) = hnsw.ef_construct {
+                hnsw_config = hnsw_config.ef_construct(ef_construct);
+            }
+            if let Some(full_scan_threshold) = hnsw.full_scan_threshold {
+                hnsw_config = hnsw_config.full_scan_threshold(full_scan_threshold);
+            }
+            if let Some(on_disk) = hnsw.on_disk {
+                hnsw_config = hnsw_config.on_disk(on_disk);
+            }
+            builder = builder.hnsw_config(hnsw_config);
+        }
+
+        match client.create_collection(builder).await {
+            Ok(_) => {
+                println!("Collection '{}' created successfully.", req.name);
+                Ok(())
+            },
+            Err(e) => {
+                println!("{}", e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+    /// Reads the search-relevant parts of a collection's vector configuration.
+    pub async fn vector_config(client: &Qdrant, collection_name: &str) -> Result<VectorConfig, String> {
+        let info = client.collection_info(collection_name).await.map_err(|e| e.to_string())?;
+        let params = info.result.and_then(|i| i.config).and_then(|c| c.params);
+
+        let bm25 = params.as_ref()
+            .and_then(|p| p.sparse_vectors_config.as_ref())
+            .map(|s| s.map.contains_key(SPARSE_VECTOR_NAME))
+            .unwrap_or(false);
+        let (distance, size) = match params.and_then(|p| p.vectors_config).and_then(|v| v.config) {
+            Some(Config::Params(params)) => (Distance::try_from(params.distance).unwrap_or(Distance::Cosine), Some(params.size)),
+            _ => (Distance::Cosine, None),
+        };
+
+        Ok(VectorConfig { bm25, distance, size })
+    }
+
+    fn meta_point_id(collection_name: &str) -> PointId {
+        PointId::from(Uuid::new_v5(&Uuid::NAMESPACE_DNS, collection_name.as_bytes()).to_string())
+    }
+
+    pub async fn load_meta(client: &Qdrant, collection_name: &str) -> Result<Option<CollectionMeta>, String> {
+        match client.collection_exists(META_COLLECTION).await {
+            Ok(true) => {},
+            Ok(false) => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        }
+
+        let request = GetPointsBuilder::new(META_COLLECTION, vec![Collection::meta_point_id(collection_name)])
+            .with_payload(true);
+        match client.get_points(request).await {
+            Ok(response) => {
+                match response.result.into_iter().next() {
+                    Some(point) => {
+                        let payload = Value::Object(Database::payload_to_map(point.payload));
+                        serde_json::from_value(payload).map(Some).map_err(|e| e.to_string())
+                    },
+                    None => Ok(None),
+                }
+            },
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub async fn save_meta(client: &Qdrant, meta: &CollectionMeta) -> Result<(), String> {
+        let exists = client.collection_exists(META_COLLECTION).await.map_err(|e| e.to_string())?;
+        if !exists {
+            let req = PostCollectionReq {
+                name: META_COLLECTION.to_string(),
+                distance: Some("dot".to_string()),
+                bm25: Some(false),
+                ..Default::default()
+            };
+            Collection::create_collection(client, &req, 1).await?;
+        }
+
+        let payload = match serde_json::to_value(meta) {
+            Ok(Value::Object(map)) => map,
+            Ok(_) => Map::new(),
+            Err(e) => return Err(e.to_string()),
+        };
+        let point = PointStruct::new(Collection::meta_point_id(&meta.collection), vec![1.0], payload);
+        match client.upsert_points(UpsertPointsBuilder::new(META_COLLECTION, vec![point])).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub async fn delete_meta(client: &Qdrant, collection_name: &str) -> Result<(), String> {
+        let exists = client.collection_exists(META_COLLECTION).await.map_err(|e| e.to_string())?;
+        if !exists {
+            return Ok(());
+        }
+
+        let ids = PointsIdsList {
+            ids: vec![Collection::meta_point_id(collection_name)],
+        };
+        match client.delete_points(DeletePointsBuilder::new(META_COLLECTION).points(ids)).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn build_info(name: String, info: CollectionInfo, meta: Option<CollectionMeta>) -> CollectionInfoResult {
+        let status = CollectionStatus::try_from(info.status)
+            .map(|s| s.as_str_name().to_lowercase())
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        let mut vector_size: Option<u64> = None;
+        let mut distance: Option<String> = None;
+        let params = info.config.and_then(|c| c.params);
+        let mut sparse_vectors: Vec<String> = params.as_ref()
+            .and_then(|p| p.sparse_vectors_config.as_ref())
+            .map(|s| s.map.keys().cloned().collect())
+            .unwrap_or_default();
+        sparse_vectors.sort();
+        let vectors_config = params
+            .and_then(|p| p.vectors_config)
+            .and_then(|v| v.config);
+        if let Some(Config::Params(params)) = vectors_config {
+            vector_size = Some(params.size);
+            distance = Distance::try_from(params.distance)
+                .ok()
+                .map(|d| d.as_str_name().to_lowercase());
+        }
+
+        let mut payload_indexes = Map::new();
+        for (field, schema) in info.payload_schema {
+            let data_type = PayloadSchemaType::try_from(schema.data_type)
+                .map(|t| t.as_str_name().to_lowercase())
+                .unwrap_or_else(|_| "unknown".to_string());
+            let index = CollectionIndexResult {
+                data_type,
+                points: schema.points,
+            };
+            payload_indexes.insert(field, serde_json::to_value(index).unwrap());
+        }
+
+        CollectionInfoResult {
+            name,
+            status,
+            points_count: info.points_count,
+            indexed_vectors_count: info.indexed_vectors_count,
+            segments_count: info.segments_count,
+            vector_size,
+            distance,
+            sparse_vectors,
+            payload_indexes,
+            meta,
+        }
+    }
+
+    /// The internal meta collection is never exposed through the API.
+    pub fn check_not_reserved(collection_name: &str) -> Result<(), String> {
+        if collection_name == META_COLLECTION {
+            return Err(format!("'{}' is reserved", META_COLLECTION));
+        }
+        Ok(())
+    }
+
+    pub async fn check_exists(client: &Qdrant, collection_name: &str) -> Result<(), HttpResponse> {
+        if let Err(e) = Collection::check_not_reserved(collection_name) {
+            return Err(HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)}));
+        }
+        match client.collection_exists(collection_name).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(HttpResponse::NotFound().json(ErrorResult {status: false, message: Some(format!("Collection '{}' does not exist", collection_name))})),
+            Err(e) => {
+                println!("{}", e);
+                Err(HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e.to_string())}))
+            }
+        }
+    }
+
+    pub async fn list() -> impl Responder {
+        let client = Database::connect();
+
+        match client.list_collections().await {
+            Ok(response) => {
+                let mut names: Vec<String> = response.collections
+                    .into_iter()
+                    .map(|c| c.name)
+                    .filter(|name| name != META_COLLECTION)
+                    .collect();
+                names.sort();
+
+                HttpResponse::Ok().json(GeneralValueResult{result: names, status: true})
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e.to_string())})
+            }
+        }
+    }
+
+    pub async fn create(data: web::Json<PostCollectionReq>) -> impl Responder {
+        if let Err(e) = Collection::check_not_reserved(&data.name) {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+        }
+
+        let client = Database::connect();
+
+        match client.collection_exists(&data.name).await {
+            Ok(false) => {},
+            Ok(true) => {
+                return HttpResponse::Conflict().json(ErrorResult {status: false, message: Some(format!("Collection '{}' already exists", data.name))});
+            },
+            Err(e) => {
+                println!("{}", e);
+                return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e.to_string())});
+            }
+        }
+
+        if data.id_strategy == Some(IdStrategy::Namespaced) && data.id_namespace_field.is_none() {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("The namespaced id strategy requires id_namespace_field".to_string())});
+        }
+
+        if let Some(name) = &data.distance {
+            if Collection::parse_distance(name).is_none() {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(format!("Unknown distance '{}'", name))});
+            }
+        }
+
+        if let Some(text_index) = &data.text_index {
+            if let Err(e) = Collection::validate_text_index(text_index) {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        }
+
+        // A named model is always probed so an explicit size cannot disagree with it
+        let mut model = data.model.clone();
+        let size = match (data.size, &model) {
+            (Some(size), None) => size,
+            (size, _) => {
+                let probe_model = model.get_or_insert(DEFAULT_EMBEDDING_MODEL.to_string()).clone();
+                let detected = match Ollama::dimension(probe_model.clone()).await {
+                    Ok(size) => size,
+                    Err(_) => {
+                        return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(format!("Could not detect vector size of model '{}'", probe_model))});
+                    }
+                };
+                if let Some(size) = size.filter(|size| *size != detected) {
+                    return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(format!("Size {} does not match model '{}', which has {} dimensions", size, probe_model, detected))});
+                }
+                detected
+            }
+        };
+
+        if let Err(e) = Collection::create_collection(&client, &data, size).await {
+            return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)});
+        }
+        if let Some(text_index) = &data.text_index {
+            if let Err(e) = Collection::create_text_index(&client, &data.name, text_index).await {
+                return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)});
+            }
+        }
+
+        let translate_to = data.translate_to.clone().filter(|t| !t.is_empty());
+        let meta = CollectionMeta {
+            collection: data.name.clone(),
+            model,
+            prompt_model: translate_to.as_ref().map(|_| DEFAULT_PROMPT_MODEL.to_string()),
+            translate_to,
+            id_strategy: data.id_strategy,
+            id_namespace_field: data.id_namespace_field.clone(),
+            dimension: size,
+            created_at: Utc::now().naive_utc(),
+        };
+        if let Err(e) = Collection::save_meta(&client, &meta).await {
+            return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)});
+        }
+
+        match client.collection_info(&data.name).await {
+            Ok(response) => {
+                match response.result {
+                    Some(info) => {
+                        let res = Collection::build_info(data.name.clone(), info, Some(meta));
+                        HttpResponse::Ok().json(GeneralValueResult{result: res, status: true})
+                    },
+                    None => HttpResponse::InternalServerError().json(ErrorResult {status: false, message: None}),
+                }
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e.to_string())})
+            }
+        }
+    }
+
+    pub async fn info(path: web::Path<String>) -> impl Responder {
+        let collection_name = path.into_inner();
+        let client = Database::connect();
+
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        let meta = match Collection::load_meta(&client, &collection_name).await {
+            Ok(meta) => meta,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        match client.collection_info(&collection_name).await {
+            Ok(response) => {
+                match response.result {
+                    Some(info) => {
+                        let res = Collection::build_info(collection_name, info, meta);
+                        HttpResponse::Ok().json(GeneralValueResult{result: res, status: true})
+                    },
+                    None => HttpResponse::NotFound().json(ErrorResult {status: false, message: Some(format!("Collection '{}' does not exist", collection_name))}),
+                }
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e.to_string())})
+            }
+        }
+    }
+
+    pub async fn put_text_index(path: web::Path<String>, data: web::Json<TextIndexReq>) -> impl Responder {
+        let collection_name = path.into_inner();
+        let client = Database::connect();
+
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+        if let Err(e) = Collection::validate_text_index(&data) {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+        }
+
+        match Collection::create_text_index(&client, &collection_name, &data).await {
+            Ok(_) => HttpResponse::Ok().json(GeneralValueResult{result: true, status: true}),
+            Err(e) => HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)}),
+        }
+    }
+
+    pub async fn delete(path: web::Path<String>) -> impl Responder {
+        let collection_name = path.into_inner();
+        let client = Database::connect();
+
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        match client.delete_collection(&collection_name).await {
+            Ok(response) => {
+                if let Err(e) = Collection::delete_meta(&client, &collection_name).await {
+                    return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)});
+                }
+                println!("Collection '{}' deleted.", collection_name);
+                HttpResponse::Ok().json(GeneralValueResult{result: response.result, status: true})
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e.to_string())})
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VectorConfig;
+    use qdrant_client::qdrant::Distance;
+
+    fn config(distance: Distance) -> VectorConfig {
+        VectorConfig { bm25: false, distance, size: Some(3) }
+    }
+
+    #[test]
+    fn similarity_metrics_take_the_stricter_cutoff() {
+        for distance in [Distance::Cosine, Distance::Dot] {
+            let config = config(distance);
+            assert!(config.is_similarity());
+            assert_eq!(config.distance_of(0.75), 0.25);
+            assert_eq!(config.score_threshold(None, None), Ok(None));
+            assert_eq!(config.score_threshold(Some(0.5), None), Ok(Some(0.5)));
+            assert_eq!(config.score_threshold(None, Some(0.25)), Ok(Some(0.75)));
+            assert_eq!(config.score_threshold(Some(0.5), Some(0.25)), Ok(Some(0.75)));
+            assert_eq!(config.score_threshold(Some(0.875), Some(0.25)), Ok(Some(0.875)));
+        }
+    }
+
+    #[test]
+    fn euclid_scores_are_distances() {
+        let config = config(Distance::Euclid);
+        assert!(!config.is_similarity());
+        assert_eq!(config.distance_of(1.5), 1.5);
+        assert_eq!(config.score_threshold(None, Some(1.5)), Ok(Some(1.5)));
+        assert!(config.score_threshold(Some(0.5), None).is_err());
+    }
+}
@@ -1,26 +1,48 @@
 use serde::{Deserialize, Serialize};
 use crate::models::general::*;
 use crate::models::database::*;
-use crate::utils::ollama::Ollama;
+use crate::models::collection::*;
+use crate::handlers::collection::{CollectionMeta, PostCollectionReq, VectorConfig, TEXT_INDEX_FIELD};
+use crate::utils::bm25::{self, SPARSE_VECTOR_NAME};
+use crate::utils::mmr;
+use crate::utils::ollama::{bounded, Ollama};
+use crate::utils::ids::{content_hash, IdStrategy};
+use crate::utils::chunking::{self, ChunkMode, ChunkingOptions};
+use crate::utils::extract::{self, FileKind};
+use crate::utils::csv_mapping::CsvMapping;
+use actix_multipart::Multipart;
+use futures::{SinkExt, StreamExt};
+use futures::channel::mpsc;
 use actix_web::*;
 use actix_web::web;
-use md5;
+use chrono::Utc;
 
 use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
 
-use qdrant_client::Qdrant;
+use qdrant_client::{Payload, Qdrant};
 use qdrant_client::qdrant::{
     UpsertPointsBuilder, 
-    CreateCollectionBuilder, 
-    Distance, 
+    GetPointsBuilder,
+    CountPointsBuilder,
+    DeletePointsBuilder,
+    ScrollPointsBuilder,
+    SetPayloadPointsBuilder,
+    DeletePayloadPointsBuilder,
+    RetrievedPoint,
     PointStruct, 
-    VectorParams, 
-    VectorsConfig, 
     QueryPointsBuilder, 
     PointId, 
     Filter,
     Condition,
     Range,
+    VectorsOutput,
+    NamedVectors,
+    Vector,
+    VectorInput,
+    ScoredPoint,
+    point_id::PointIdOptions,
+    vector_output,
 };
 use uuid::Uuid;
 
@@ -39,6 +61,21 @@ pub enum DataType {
     ItemList(Vec<PostDatabaseItem>),
 }
 
+impl DataType {
+    pub fn items(&self) -> Vec<PostDatabaseItem> {
+        match self {
+            DataType::StringList(list) => list.iter()
+                .map(|s| PostDatabaseItem {
+                    id: None,
+                    text: s.clone(),
+                    metadata: None,
+                })
+                .collect(),
+            DataType::ItemList(list) => list.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct PostDatabaseReq {
     pub data: DataType,
@@ -46,6 +83,38 @@ pub struct PostDatabaseReq {
     pub collection: String,
     pub translate_to: Option<String>,
     pub calculate_nearest: Option<usize>,
+    pub id_strategy: Option<IdStrategy>,
+    pub id_namespace_field: Option<String>,
+    pub chunking: Option<ChunkingOptions>,
+    pub dedupe_threshold: Option<f32>,
+    pub dedupe_action: Option<DedupeAction>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingStatus {
+    Embedded,
+    Reused,
+}
+
+/// What happens to an item whose nearest stored neighbour is closer than `dedupe_threshold`.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupeAction {
+    /// The item is not stored
+    #[default]
+    Skip,
+    /// The item is not stored; its metadata keys are added to the neighbour
+    Merge,
+    /// The item is stored with a `duplicate_of` payload pointing at the neighbour
+    Link,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DuplicateResult {
+    pub action: DedupeAction,
+    pub duplicate_of: String,
+    pub distance: f32,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -55,8 +124,34 @@ pub struct PostDatabaseResult {
     pub english: Option<String>,
     pub embeddings: Vec<f32>,
     pub id: String,
+    pub embedding_status: EmbeddingStatus,
+    pub duplicate: Option<DuplicateResult>,
+}
+
+/// `id` names the uploaded document. With chunking it is the `parent_id` of the chunks
+/// listed in `items`, and the item endpoints resolve it to them.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct UploadDatabaseResult {
+    pub id: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: usize,
+    pub items: Vec<PostDatabaseResult>,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CsvRowError {
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CsvImportResult {
+    pub rows: usize,
+    pub inserted: usize,
+    pub failed: usize,
+    pub errors: Vec<CsvRowError>,
+}
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct PostEmbeddingsItem {
@@ -71,9 +166,55 @@ pub struct PostEmbeddingsItem {
 pub struct PostEmbeddingsReq {
     pub data: Vec<PostEmbeddingsItem>,
     pub collection: String,
+    pub model: Option<String>,
+    pub calculate_nearest: Option<usize>,
+}
+
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ImportDatabaseQuery {
+    pub model: Option<String>,
+    pub translate_to: Option<String>,
+    pub batch_size: Option<usize>,
     pub calculate_nearest: Option<usize>,
+    pub id_strategy: Option<IdStrategy>,
+    pub id_namespace_field: Option<String>,
+    pub dedupe_threshold: Option<f32>,
+    pub dedupe_action: Option<DedupeAction>,
+}
+
+/// A line of an NDJSON import; lines carrying `embeddings` skip the embedding step.
+#[derive(Clone)]
+pub enum ImportLine {
+    Embeddings(PostEmbeddingsItem),
+    Item(PostDatabaseItem),
+}
+
+impl ImportLine {
+    /// A line with an `embeddings` key must be a valid `PostEmbeddingsItem`; it never
+    /// falls back to re-embedding the text.
+    pub fn parse(line: &[u8]) -> Result<ImportLine, String> {
+        let value: Value = serde_json::from_slice(line).map_err(|e| e.to_string())?;
+        if value.get("embeddings").is_some() {
+            serde_json::from_value(value)
+                .map(ImportLine::Embeddings)
+                .map_err(|e| format!("Invalid embeddings line: {}", e))
+        } else {
+            serde_json::from_value(value)
+                .map(ImportLine::Item)
+                .map_err(|e| e.to_string())
+        }
+    }
 }
 
+/// Streamed back to the client as NDJSON while an import runs.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImportEvent {
+    Progress { lines: usize, inserted: usize, failed: usize },
+    Error { line: usize, message: String },
+    Done { lines: usize, inserted: usize, failed: usize },
+}
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct FindDatabaseFilterReq {
@@ -93,417 +234,1901 @@ pub struct FindDatabaseReq {
     pub filter_by_ids: Option<Vec<String>>,
     pub limit: Option<usize>,
     pub offset: Option<usize>,
+    pub collapse: Option<bool>,
+    pub mode: Option<SearchMode>,
+    pub fusion: Option<FusionMethod>,
+    /// Share of the dense ranking in the fused score, 0.5 by default
+    pub dense_weight: Option<f32>,
+    /// Words every result must contain; needs the collection's text index
+    pub keywords: Option<String>,
+    /// Reranks this many candidates with `rerank_model` before paging
+    pub rerank_top_n: Option<usize>,
+    pub rerank_model: Option<String>,
+    /// Drops results farther than this distance
+    pub max_distance: Option<f32>,
+    /// Drops results below this similarity; cosine and dot collections only
+    pub min_score: Option<f32>,
+    /// Reorders by maximal marginal relevance; 0 keeps the relevance order, 1 spreads results out the most
+    pub diversity: Option<f32>,
+    /// Candidates diversification picks from, 4 times limit + offset by default
+    pub candidate_pool: Option<usize>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    #[default]
+    Dense,
+    /// Dense vectors fused with the BM25 sparse vector
+    Hybrid,
+    /// Full-text match only, ranked by BM25 when the collection has it
+    Keyword,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionMethod {
+    /// Reciprocal rank fusion
+    #[default]
+    Rrf,
+    /// Min-max normalized scores, weighted by `dense_weight`
+    Weighted,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct FindDatabaseResult {
     pub id: String,
+    pub point_id: String,
     pub text: String,
     pub metadata: Option<Map<String, Value>>,
-    pub distance: f32,
+    /// Distance to the query vector; `null` when the search had none, as in keyword mode
+    pub distance: Option<f32>,
+    /// Fused score of hybrid search, or the BM25 score of keyword search
+    pub score: Option<f32>,
+    /// Relevance from 0 to 1 graded by the rerank model
+    pub rerank_score: Option<f32>,
+    #[serde(skip)]
+    pub vector: Option<Vec<f32>>,
 }
 
-const DEFAULT_EMBEDDING_MODEL: &str = "bge-m3";
-const DEFAULT_PROMPT_MODEL: &str = "gemma2";
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GetDatabaseItemQuery {
+    pub with_vectors: Option<bool>,
+}
 
-fn translate_prompt(prompt: String, lang: String) -> String {
-    let res = format!("Please translate below text to {} without any extra explation and type exact text if It already translated:\n{}", lang.as_str(), prompt.as_str());
-    res.to_string()
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GetDatabaseItemsReq {
+    pub ids: Vec<String>,
+    pub with_vectors: Option<bool>,
 }
 
-impl Database {
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GetDatabaseResult {
+    pub id: String,
+    pub point_id: String,
+    pub text: String,
+    pub metadata: Option<Map<String, Value>>,
+    pub embeddings: Option<Vec<f32>>,
+}
 
-    pub fn get_qdrant_port() -> i32 {
-        use std::env;
-        env::var("QDRANT_PORT").unwrap_or("6334".to_string()).parse().unwrap()
-    }
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScrollDatabaseReq {
+    pub collection: String,
+    pub filters: Option<Vec<FindDatabaseFilterReq>>,
+    pub filter_by_ids: Option<Vec<String>>,
+    pub limit: Option<u32>,
+    pub cursor: Option<String>,
+    pub with_vectors: Option<bool>,
+}
 
-    async fn create_client(collection_name: String) -> Qdrant {
-        let client = Qdrant::from_url(&("http://localhost:".to_string() + &Database::get_qdrant_port().to_string())).build().unwrap();
-        if !client.collection_exists(&collection_name).await.unwrap() {
-            println!("There is no '{}' collection. Creating...", collection_name);
-            client
-                .create_collection(
-                    CreateCollectionBuilder::new(collection_name.clone()).vectors_config(
-                        VectorsConfig {
-                            config: Some(qdrant_client::qdrant::vectors_config::Config::Params(
-                                VectorParams {
-                                    size: 1024,
-                                    distance: Distance::Cosine.into(),
-                                    ..Default::default()
-                                },
-                            )),
-                        }
-                    )).await.unwrap();
-            println!("Collection '{}' created successfully.", collection_name);
-        }
- 
-        client
-    }
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScrollDatabaseResult {
+    pub items: Vec<GetDatabaseResult>,
+    pub next_cursor: Option<String>,
+}
 
-	pub async fn insert(data: web::Json<PostDatabaseReq>) -> impl Responder {
-        let model = data.model.clone().unwrap_or(DEFAULT_EMBEDDING_MODEL.to_string());
-
-        let mut data_list: Vec<PostDatabaseItem> = Vec::new();
-        match &data.data {
-            DataType::StringList(list) => {
-                for s in list {
-                    data_list.push(PostDatabaseItem {
-                        id: None,
-                        text: s.clone(),
-                        metadata: None,
-                    });
-                }
-            }
-            DataType::ItemList(list) => {
-                data_list = list.clone();
-            }
-        }
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CountDatabaseReq {
+    pub collection: String,
+    pub filters: Option<Vec<FindDatabaseFilterReq>>,
+    pub filter_by_ids: Option<Vec<String>>,
+    pub exact: Option<bool>,
+}
 
-        let mut results: Vec<PostDatabaseResult> = Vec::new();
-        let mut embeddings_list: Vec<Vec<f32>> = Vec::new();
-        if data.translate_to != None && data.translate_to != Some(String::new()) {
-            for item in data_list.clone() {
-                let t: String = item.text;
-                let prompt = translate_prompt(t.clone(), data.translate_to.clone().unwrap());
-                let english = Ollama::generate(prompt, DEFAULT_PROMPT_MODEL.to_string()).await.unwrap();
-                let embeddings = Ollama::embedding(english.clone(), model.clone()).await;
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CountDatabaseResult {
+    pub count: u64,
+    pub exact: bool,
+}
 
-                let md5 = md5::compute(t.clone());
-                let id_hash: String = format!("{:x}", md5);
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeleteDatabaseReq {
+    pub ids: Option<Vec<String>>,
+    pub filters: Option<Vec<FindDatabaseFilterReq>>,
+    pub dry_run: Option<bool>,
+}
 
-                embeddings_list.push(embeddings.clone().unwrap());
-                results.push(PostDatabaseResult {
-                    id: id_hash,
-                    text: t,
-                    metadata: item.metadata,
-                    english: Some(english),
-                    embeddings: embeddings.unwrap(),
-                });
-            }
-        } else {
-            for item in data_list.clone() {
-                let t: String = item.text;
-                let id: String = match item.id {
-                    Some(id) => id,
-                    None => {
-                        let md5 = md5::compute(t.clone());
-                        format!("{:x}", md5)
-                    }
-                };
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DeleteDatabaseResult {
+    pub deleted: u64,
+    pub dry_run: bool,
+    pub ids: Option<Vec<String>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PatchDatabaseReq {
+    pub ids: Option<Vec<String>>,
+    pub filters: Option<Vec<FindDatabaseFilterReq>>,
+    pub set: Option<Map<String, Value>>,
+    pub remove: Option<Vec<String>>,
+    pub replace: Option<bool>,
+}
 
-                let embeddings = Ollama::embedding(t.clone(), model.clone()).await;
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PatchDatabaseResult {
+    pub updated: u64,
+}
 
-                embeddings_list.push(embeddings.clone().unwrap());
-                results.push(PostDatabaseResult {
-                    id: id,
-                    text: t,
-                    metadata: item.metadata,
-                    english: None,
-                    embeddings: embeddings.unwrap(),
-                });
-            }
+/// Embedding and translation settings a request runs with, after merging it
+/// with what the collection recorded at creation time.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EmbeddingSettings {
+    pub model: String,
+    pub translate_to: Option<String>,
+    pub prompt_model: String,
+}
+
+/// Per-request knobs of the insert pipeline, already merged with the collection's defaults.
+#[derive(Clone, Default)]
+pub struct InsertOptions {
+    pub calculate_nearest: Option<usize>,
+    pub id_strategy: IdStrategy,
+    pub id_namespace_field: Option<String>,
+    pub chunking: Option<ChunkingOptions>,
+    pub dedupe_threshold: Option<f32>,
+    pub dedupe_action: DedupeAction,
+}
+
+impl InsertOptions {
+    pub fn with_dedupe(self, threshold: Option<f32>, action: Option<DedupeAction>) -> InsertOptions {
+        InsertOptions {
+            dedupe_threshold: threshold,
+            dedupe_action: action.unwrap_or_default(),
+            ..self
         }
+    }
+}
 
-        let collection_name: String = data.collection.clone();
-        let client = Database::create_client(collection_name.clone()).await;
+pub struct UploadedFile {
+    pub filename: String,
+    pub mime_type: String,
+    pub bytes: Vec<u8>,
+}
 
-        let mut ids: Vec<&str> = Vec::new();
-        let mut documents: Vec<&str> = Vec::new();
-        let mut embeddings: Vec<Vec<f32>> = Vec::new();
-        let mut metadatas: Vec<Map<String, Value>> = Vec::new();
-        let mut idx = 0;
-        for r in &mut results {
-            let mut metadata = match r.metadata.clone() {
-                Some(map) => map,
-                None => Map::new(),
-            };
+/// A document ready to be written as a Qdrant point.
+struct StoredDocument {
+    id: String,
+    text: String,
+    english: Option<String>,
+    embeddings: Vec<f32>,
+    metadata: Map<String, Value>,
+    content_hash: Option<String>,
+    model: Option<String>,
+}
 
-            if data.calculate_nearest != None {
-                let mut mid_distance: f32 = 0.0;
-                let nearests = Database::find_nearest(collection_name.clone(), Some(embeddings_list.get(idx).unwrap().clone()), data.calculate_nearest, None, None, None).await;
-                match nearests {
-                    Ok (list) => {
-                        let mut len: f32 = 0.0;
-                        for n in list {
-                            mid_distance = mid_distance + n.distance;
-                            len = len + 1.0;
-                        }
-                        mid_distance = mid_distance / len;
-                    },
-                    Err(_) => {}
-                }
-                metadata.insert("mid_distance".to_string(), Value::from(mid_distance));
-            }
-            r.metadata = Some(metadata.clone());
+/// Filters, cutoff and metric of a dense search.
+#[derive(Clone)]
+struct NearestOptions {
+    config: VectorConfig,
+    filters: Option<Vec<FindDatabaseFilterReq>>,
+    filter_by_ids: Option<Vec<String>>,
+    score_threshold: Option<f32>,
+}
 
-            ids.push(r.id.as_str());
-            documents.push(&r.text.as_str());
-            embeddings.push(embeddings_list.get(idx).unwrap().clone());
-            metadatas.push(metadata);
-            idx = idx + 1;
+impl NearestOptions {
+    fn unfiltered(config: VectorConfig) -> NearestOptions {
+        NearestOptions {
+            config,
+            filters: None,
+            filter_by_ids: None,
+            score_threshold: None,
         }
+    }
+}
 
-        let mut points_to_upsert: Vec<PointStruct> = Vec::new();
-        for i in 0..ids.len() {
-            let mut payload = metadatas[i].clone();
-            payload.insert(
-                "original_document".to_string(),
-                json!(documents[i]),
-            );
+/// Payload keys naomi-db manages itself; metadata updates may not touch them.
+pub const RESERVED_PAYLOAD_KEYS: &[&str] = &[
+    "original_document",
+    "original_id",
+    "translated_document",
+    "content_hash",
+    "embedding_model",
+];
 
-            let point_id =
-                Uuid::new_v5(&Uuid::NAMESPACE_DNS, ids[i].as_bytes()).to_string();
+/// Metadata keys linking a chunk to its parent document. They stay visible in results
+/// but, like the reserved keys, metadata updates may not touch them.
+pub const CHUNK_PAYLOAD_KEYS: &[&str] = &["parent_id", "chunk_index", "chunk_start", "chunk_end"];
 
-            let point = PointStruct::new(
-                point_id,
-                embeddings[i].clone(),
-                payload,
-            );
-            points_to_upsert.push(point);
-        }
+pub const DEFAULT_UPLOAD_MAX_BYTES: usize = 10 * 1024 * 1024;
+/// Limit on all parts of one multipart request together.
+pub const DEFAULT_UPLOAD_MAX_TOTAL_BYTES: usize = 50 * 1024 * 1024;
 
-        if !points_to_upsert.is_empty() {
-            let _result = client
-                .upsert_points(UpsertPointsBuilder::new(collection_name, points_to_upsert))
-                .await.unwrap();
-        } else {
+pub const DEFAULT_IMPORT_BATCH_SIZE: usize = 64;
+pub const MAX_IMPORT_BATCH_SIZE: usize = 1024;
+
+/// `k` of reciprocal rank fusion; dampens the weight of the very first ranks.
+pub const RRF_K: f32 = 60.0;
+
+pub const DEFAULT_EMBEDDING_MODEL: &str = "bge-m3";
+pub const DEFAULT_PROMPT_MODEL: &str = "gemma2";
+
+/// State of a running NDJSON import. Lines are buffered per kind and flushed
+/// in batches so memory stays bounded no matter how large the body is.
+struct NdjsonImport {
+    client: Qdrant,
+    collection_name: String,
+    settings: EmbeddingSettings,
+    meta: Option<CollectionMeta>,
+    options: InsertOptions,
+    model: Option<String>,
+    batch_size: usize,
+    items: Vec<(usize, PostDatabaseItem)>,
+    embeddings: Vec<(usize, PostEmbeddingsItem)>,
+    lines: usize,
+    inserted: usize,
+    failed: usize,
+    events: mpsc::Sender<Result<web::Bytes, Error>>,
+}
+
+impl NdjsonImport {
+    /// Sends an event to the client; fails once the client has gone away.
+    async fn emit(&mut self, event: ImportEvent) -> Result<(), ()> {
+        let mut line = serde_json::to_vec(&event).unwrap_or_default();
+        line.push(b'\n');
+        self.events.send(Ok(web::Bytes::from(line))).await.map_err(|_| ())
+    }
+
+    async fn fail(&mut self, line: usize, message: String) -> Result<(), ()> {
+        self.failed += 1;
+        self.emit(ImportEvent::Error { line, message }).await
+    }
+
+    async fn progress(&mut self) -> Result<(), ()> {
+        let event = ImportEvent::Progress { lines: self.lines, inserted: self.inserted, failed: self.failed };
+        self.emit(event).await
+    }
+
+    async fn push_line(&mut self, number: usize, line: &[u8]) -> Result<(), ()> {
+        self.lines = number;
+        match ImportLine::parse(line) {
+            Ok(ImportLine::Embeddings(item)) => {
+                let expected_size = self.meta.as_ref().map(|m| m.dimension)
+                    .or(self.embeddings.first().map(|(_, first)| first.embeddings.len() as u64));
+                if let Err(e) = Database::check_dimensions(&self.collection_name, expected_size, std::slice::from_ref(&item)) {
+                    return self.fail(number, e).await;
+                }
+                self.embeddings.push((number, item));
+                if self.embeddings.len() >= self.batch_size {
+                    return self.flush_embeddings().await;
+                }
+            },
+            Ok(ImportLine::Item(item)) => {
+                self.items.push((number, item));
+                if self.items.len() >= self.batch_size {
+                    return self.flush_items().await;
+                }
+            },
+            Err(e) => return self.fail(number, e).await,
         }
+        Ok(())
+    }
 
-        HttpResponse::Ok().json(GeneralValueResult{result: results, status: true})
-	} 
+    async fn flush_items(&mut self) -> Result<(), ()> {
+        if self.items.is_empty() {
+            return Ok(());
+        }
+        let (numbers, items): (Vec<usize>, Vec<PostDatabaseItem>) = std::mem::take(&mut self.items).into_iter().unzip();
+        match Database::insert_items(&self.client, &self.collection_name, &self.settings, &mut self.meta, &self.options, items).await {
+            Ok(_) => self.inserted += numbers.len(),
+            Err(e) => {
+                for number in numbers {
+                    self.fail(number, e.clone()).await?;
+                }
+            }
+        }
+        self.progress().await
+    }
 
-	pub async fn insert_embeddings(data: web::Json<PostEmbeddingsReq>) -> impl Responder {
-        let collection_name: String = data.collection.clone();
-        let client = Database::create_client(collection_name.clone()).await;
+    async fn flush_embeddings(&mut self) -> Result<(), ()> {
+        if self.embeddings.is_empty() {
+            return Ok(());
+        }
+        let (numbers, items): (Vec<usize>, Vec<PostEmbeddingsItem>) = std::mem::take(&mut self.embeddings).into_iter().unzip();
+        match Database::insert_embedding_items(&self.client, &self.collection_name, &self.settings, &mut self.meta, self.model.clone(), items, self.options.calculate_nearest).await {
+            Ok(_) => self.inserted += numbers.len(),
+            Err(e) => {
+                for number in numbers {
+                    self.fail(number, e.clone()).await?;
+                }
+            }
+        }
+        self.progress().await
+    }
 
-        let mut result: Vec<String> = Vec::new();
-        let mut documents_strs: Vec<String> = Vec::new();
+    async fn run(mut self, mut body: web::Payload) {
+        let max_line = Database::get_upload_max_bytes();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut number: usize = 0;
 
-        let mut ids: Vec<&str> = Vec::new();
-        let mut embeddings: Vec<Vec<f32>> = Vec::new();
-        let mut documents: Vec<&str> = Vec::new();
-        let mut metadatas: Vec<Map<String, Value>> = Vec::new();
-        
-        for item in data.data.clone() {
-            let mut metadata = match item.metadata.clone() {
-                Some(map) => map,
-                None => Map::new(),
+        while let Some(chunk) = body.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    let _ = self.fail(number + 1, e.to_string()).await;
+                    return;
+                }
             };
+            buffer.extend_from_slice(&chunk);
 
-            if data.calculate_nearest != None {
-                let mut mid_distance: f32 = 0.0;
-                let nearests = Database::find_nearest(collection_name.clone(), Some(item.embeddings.clone()), data.calculate_nearest, None, None, None).await;
-                match nearests {
-                    Ok (list) => {
-                        let mut len: f32 = 0.0;
-                        for n in list {
-                            mid_distance = mid_distance + n.distance;
-                            len = len + 1.0;
-                        }
-                        mid_distance = mid_distance / len;
-                    },
-                    Err(_) => {}
+            while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                number += 1;
+                if line.iter().all(|b| b.is_ascii_whitespace()) {
+                    continue;
+                }
+                if self.push_line(number, &line).await.is_err() {
+                    println!("Import into '{}' aborted: client disconnected", self.collection_name);
+                    return;
                 }
-                metadata.insert("mid_distance".to_string(), Value::from(mid_distance));
             }
 
-            result.push(item.id);
-            documents_strs.push(item.text);
-            embeddings.push(item.embeddings.clone());
-            metadatas.push(metadata);
+            if buffer.len() > max_line {
+                let _ = self.fail(number + 1, format!("Line is longer than {} bytes", max_line)).await;
+                return;
+            }
         }
-        for id in &result {
-            ids.push(id.as_str());
+
+        if !buffer.iter().all(|b| b.is_ascii_whitespace()) {
+            number += 1;
+            if self.push_line(number, &buffer).await.is_err() {
+                return;
+            }
         }
-        for str in &documents_strs {
-            documents.push(str.as_str());
+        if self.flush_items().await.is_err() || self.flush_embeddings().await.is_err() {
+            return;
         }
 
-        let mut points_to_upsert: Vec<PointStruct> = Vec::new();
-        for i in 0..ids.len() {
-            let mut payload = metadatas[i].clone();
-            payload.insert(
-                "original_document".to_string(),
-                json!(documents[i]),
-            );
+        let event = ImportEvent::Done { lines: number, inserted: self.inserted, failed: self.failed };
+        let _ = self.emit(event).await;
+    }
+}
 
-            let point_id =
-                Uuid::new_v5(&Uuid::NAMESPACE_DNS, ids[i].as_bytes()).to_string();
+fn translate_prompt(prompt: String, lang: String) -> String {
+    let res = format!("Please translate below text to {} without any extra explation and type exact text if It already translated:\n{}", lang.as_str(), prompt.as_str());
+    res.to_string()
+}
 
-            let point = PointStruct::new(
-                point_id,
-                embeddings[i].clone(),
-                payload,
-            );
-            points_to_upsert.push(point);
-        }
+fn rerank_prompt(query: &str, document: &str) -> String {
+    format!("Grade how relevant the document is to the search query on a scale from 0 (unrelated) to 10 (answers it exactly). Reply with the number only.\n\nQuery: {}\n\nDocument: {}", query, document)
+}
 
-        if !points_to_upsert.is_empty() {
-            let _result = client
-                .upsert_points(UpsertPointsBuilder::new(collection_name, points_to_upsert))
-                .await.unwrap();
-        } else {
-        }
+/// Reads the first number of a grading reply as a 0 to 1 score.
+fn parse_rerank_grade(reply: &str) -> Option<f32> {
+    let start = reply.find(|c: char| c.is_ascii_digit())?;
+    let number: String = reply[start..].chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+    number.trim_end_matches('.').parse::<f32>().ok().map(|grade| (grade / 10.0).clamp(0.0, 1.0))
+}
 
-        HttpResponse::Ok().json(GeneralValueResult{result: result, status: true})
-	} 
+impl Database {
 
-    async fn find_nearest(collection_name: String, embedding: Option<Vec<f32>>, limit: Option<usize>, offset: Option<usize>, filters: Option<Vec<FindDatabaseFilterReq>>, filter_by_ids: Option<Vec<String>>) -> Result<Vec<FindDatabaseResult>, bool> {
-        let client = Database::create_client(collection_name.clone()).await;
-        let mut filter_conditions: Vec<Condition> = Vec::new();
+    pub fn get_qdrant_port() -> i32 {
+        use std::env;
+        env::var("QDRANT_PORT").unwrap_or("6334".to_string()).parse().unwrap()
+    }
 
-        match filters {
-            Some(list) => {
-                for l in list {
-                    match l.operator.as_str() {
-                        ">" => {
-                            filter_conditions.push(Condition::range(
-                                l.field,
-                                Range {
-                                    gt: Some(l.value.as_f64().unwrap()),
-                                    ..Default::default()
-                                },
-                            ));
-                        },
-                        ">=" => {
-                            filter_conditions.push(Condition::range(
-                                l.field,
-                                Range {
-                                    gte: Some(l.value.as_f64().unwrap()),
-                                    ..Default::default()
-                                },
-                            ));
-                        },
-                        "<" => {
-                            filter_conditions.push(Condition::range(
-                                l.field,
-                                Range {
-                                    lt: Some(l.value.as_f64().unwrap()),
-                                    ..Default::default()
-                                },
-                            ));
-                        },
-                        "<=" => {
-                            filter_conditions.push(Condition::range(
-                                l.field,
-                                Range {
-                                    lte: Some(l.value.as_f64().unwrap()),
-                                    ..Default::default()
-                                },
-                            ));
-                        },
-                        "=" => {
-                            match l.value.as_i64() {
-                                Some(v) => {
-                                    filter_conditions.push(Condition::matches(
-                                        l.field,
-                                        v,
-                                    ));
-                                },
-                                None => {
-                                    filter_conditions.push(Condition::matches_text(
-                                        l.field,
-                                        l.value.as_str().unwrap(),
-                                    ));
-                                }
-                            }
-                        },
-                        _ => {
-                        }
-                    }
-                }
-            },
-            None => {}
-        }
-        
-        if let Some(ids) = filter_by_ids {
-        if !ids.is_empty() {
-            let point_ids: Vec<PointId> = ids.into_iter().map(PointId::from).collect();
-            filter_conditions.push(Condition::has_id(point_ids));
-        }
+    pub fn get_upload_max_bytes() -> usize {
+        use std::env;
+        env::var("UPLOAD_MAX_BYTES").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_UPLOAD_MAX_BYTES)
     }
 
-        let filter = Filter::must(filter_conditions);
+    pub fn get_upload_max_total_bytes() -> usize {
+        use std::env;
+        env::var("UPLOAD_MAX_TOTAL_BYTES").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_UPLOAD_MAX_TOTAL_BYTES)
+    }
 
-        let mut search_request = QueryPointsBuilder::new(collection_name);
-        match embedding {
-            Some(embd) => {
-                search_request = search_request.query(embd);
-            },
-            None => {},
-        };
+    pub fn connect() -> Qdrant {
+        Qdrant::from_url(&("http://localhost:".to_string() + &Database::get_qdrant_port().to_string())).build().unwrap()
+    }
 
-        search_request = search_request.filter(filter)
-            .limit(limit.unwrap_or(10) as u64)
-            .offset(offset.unwrap_or(0) as u64)
-            .with_payload(true)
-            .with_vectors(true);
+    /// Maps a user-facing document id to the UUIDv5 point id it is stored under.
+    pub fn point_id(id: &str) -> String {
+        Uuid::new_v5(&Uuid::NAMESPACE_DNS, id.as_bytes()).to_string()
+    }
 
-        let search_result = client.query(search_request).await;
+    fn point_id_to_string(point_id: Option<PointId>) -> String {
+        match point_id {
+            Some(PointId { point_id_options: Some(PointIdOptions::Num(n)) }) => n.to_string(),
+            Some(PointId { point_id_options: Some(PointIdOptions::Uuid(s)) }) => s,
+            _ => "".to_string(),
+        }
+    }
 
-        match search_result {
-            Ok(response) => {
-                let mut list: Vec<FindDatabaseResult> = Vec::new();
+    fn take_document(payload: &mut HashMap<String, qdrant_client::qdrant::Value>) -> String {
+        payload
+            .remove("original_document")
+            .and_then(|v| v.as_str().cloned())
+            .unwrap_or_default()
+    }
 
-                for scored_point in response.result {
-                    let mut payload = scored_point.payload;
+    /// Takes the caller's id out of the payload, falling back to the point id
+    /// for documents stored before ids were kept.
+    fn take_original_id(payload: &mut HashMap<String, qdrant_client::qdrant::Value>, point_id: &str) -> String {
+        payload
+            .remove("original_id")
+            .and_then(|v| v.as_str().cloned())
+            .unwrap_or(point_id.to_string())
+    }
 
-                    let text = payload
-                        .remove("original_document")
-                        .and_then(|v| Some(v.to_string()))
-                        .unwrap_or_else(|| "".to_string());
+    /// With `bm25` the point also carries the sparse term weights of its text.
+    fn build_point(document: &StoredDocument, bm25: bool) -> PointStruct {
+        let mut payload = document.metadata.clone();
+        payload.insert(
+            "original_document".to_string(),
+            json!(document.text),
+        );
+        payload.insert(
+            "original_id".to_string(),
+            json!(document.id),
+        );
+        if let Some(english) = &document.english {
+            payload.insert("translated_document".to_string(), json!(english));
+        }
+        if let Some(content_hash) = &document.content_hash {
+            payload.insert("content_hash".to_string(), json!(content_hash));
+        }
+        if let Some(model) = &document.model {
+            payload.insert("embedding_model".to_string(), json!(model));
+        }
 
-                    let metadata = payload;
+        let (indices, values) = bm25::document_vector(&document.text);
+        if !bm25 || indices.is_empty() {
+            return PointStruct::new(
+                Database::point_id(&document.id),
+                document.embeddings.clone(),
+                payload,
+            );
+        }
 
-                    let id_str = match scored_point.id.unwrap() {
-                        PointId { point_id_options: Some(qdrant_client::qdrant::point_id::PointIdOptions::Num(n)) } => n.to_string(),
-                        PointId { point_id_options: Some(qdrant_client::qdrant::point_id::PointIdOptions::Uuid(s)) } => s,
-                        _ => "".to_string(),
-                    };
+        let vectors = NamedVectors::default()
+            .add_vector("", document.embeddings.clone())
+            .add_vector(SPARSE_VECTOR_NAME, Vector::new_sparse(indices, values));
+        PointStruct::new(
+            Database::point_id(&document.id),
+            vectors,
+            payload,
+        )
+    }
 
-                    list.push(FindDatabaseResult {
-                        id: id_str,
-                        metadata: Some(metadata.into_iter()
-                            .map(|(k, v)| {
-                                let serialized_value = serde_json::to_value(v).expect("Failed to serialize value");
-                                (k, serialized_value)
-                            })
-                            .collect()),
-                        text: text,
-                        distance: 1.0 - scored_point.score,
-                    });
-                }
+    /// Splits the user metadata from the payload, dropping the keys naomi-db manages.
+    fn take_metadata(payload: HashMap<String, qdrant_client::qdrant::Value>) -> Map<String, Value> {
+        let mut metadata = Database::payload_to_map(payload);
+        for key in RESERVED_PAYLOAD_KEYS {
+            metadata.remove(*key);
+        }
+        metadata
+    }
 
-                Ok(list)
-            },
-            Err(e) => {
-                println!("{}", e);
-                Err(false)
-            }
+    fn dense_vector(vectors: Option<VectorsOutput>) -> Option<Vec<f32>> {
+        match vectors?.get_vector_by_name("")? {
+            vector_output::Vector::Dense(dense) => Some(dense.data),
+            _ => None,
         }
     }
 
-	pub async fn find(data: web::Json<FindDatabaseReq>) -> impl Responder {
-        let model = data.model.clone().unwrap_or(DEFAULT_EMBEDDING_MODEL.to_string());
-        
-        let embedding: Option<Vec<f32>> = match &data.text {
-            Some(text) => {
-                if data.translate_to != None && data.translate_to != Some(String::new()) {
-                    let prompt = translate_prompt(text.clone(), data.translate_to.clone().unwrap());
-                    let english = Ollama::generate(prompt, DEFAULT_PROMPT_MODEL.to_string()).await.unwrap();
-                    let embeddings = Ollama::embedding(english.clone(), model.clone()).await;
-                    Some(embeddings.unwrap())
-                } else {
-                    let embeddings = Ollama::embedding(text.clone(), model.clone()).await;
-                    Some(embeddings.unwrap())
-                }
+    pub fn payload_to_map(payload: HashMap<String, qdrant_client::qdrant::Value>) -> Map<String, Value> {
+        payload.into_iter()
+            .map(|(k, v)| {
+                let serialized_value = serde_json::to_value(v).expect("Failed to serialize value");
+                (k, serialized_value)
+            })
+            .collect()
+    }
+
+    fn resolve_settings(collection_name: &str, meta: &Option<CollectionMeta>, model: Option<String>, translate_to: Option<String>) -> Result<EmbeddingSettings, String> {
+        let translate_to = translate_to.filter(|t| !t.is_empty());
+
+        let meta = match meta {
+            Some(meta) => meta,
+            None => {
+                return Ok(EmbeddingSettings {
+                    model: model.unwrap_or(DEFAULT_EMBEDDING_MODEL.to_string()),
+                    prompt_model: DEFAULT_PROMPT_MODEL.to_string(),
+                    translate_to,
+                });
+            }
+        };
+
+        let model = match (&meta.model, model) {
+            (Some(stored), Some(requested)) if *stored != requested => {
+                return Err(format!("Collection '{}' uses embedding model '{}', but '{}' was requested", collection_name, stored, requested));
             },
-            None => None,
+            (Some(stored), _) => stored.clone(),
+            (None, requested) => requested.unwrap_or(DEFAULT_EMBEDDING_MODEL.to_string()),
         };
 
+        if translate_to.is_some() && translate_to != meta.translate_to {
+            return Err(format!("Collection '{}' was created with translate_to {:?}, but {:?} was requested", collection_name, meta.translate_to, translate_to));
+        }
+
+        Ok(EmbeddingSettings {
+            model,
+            translate_to: meta.translate_to.clone(),
+            prompt_model: meta.prompt_model.clone().unwrap_or(DEFAULT_PROMPT_MODEL.to_string()),
+        })
+    }
+
+    pub async fn load_settings(client: &Qdrant, collection_name: &str, model: Option<String>, translate_to: Option<String>) -> Result<(EmbeddingSettings, Option<CollectionMeta>), HttpResponse> {
+        if let Err(e) = Collection::check_not_reserved(collection_name) {
+            return Err(HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)}));
+        }
+        let meta = match Collection::load_meta(client, collection_name).await {
+            Ok(meta) => meta,
+            Err(e) => {
+                println!("{}", e);
+                return Err(HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)}));
+            }
+        };
+
+        match Database::resolve_settings(collection_name, &meta, model, translate_to) {
+            Ok(settings) => Ok((settings, meta)),
+            Err(e) => Err(HttpResponse::Conflict().json(ErrorResult {status: false, message: Some(e)})),
+        }
+    }
+
+    pub async fn record_meta(client: &Qdrant, collection_name: &str, settings: &EmbeddingSettings, model: Option<String>, dimension: u64) -> Result<CollectionMeta, String> {
+        let meta = CollectionMeta {
+            collection: collection_name.to_string(),
+            model,
+            translate_to: settings.translate_to.clone(),
+            prompt_model: settings.translate_to.as_ref().map(|_| settings.prompt_model.clone()),
+            id_strategy: None,
+            id_namespace_field: None,
+            dimension,
+            created_at: Utc::now().naive_utc(),
+        };
+        Collection::save_meta(client, &meta).await?;
+        Ok(meta)
+    }
+
+    /// Records the metadata of a collection after its first successful write and returns
+    /// the record as stored. A missing record takes the collection's real dimension; a
+    /// record without a model takes `model`.
+    async fn record_first_write(client: &Qdrant, collection_name: &str, settings: &EmbeddingSettings, meta: &Option<CollectionMeta>, model: Option<String>) -> Result<Option<CollectionMeta>, String> {
+        match meta {
+            Some(meta) if meta.model.is_none() && model.is_some() => {
+                let meta = CollectionMeta { model, ..meta.clone() };
+                Collection::save_meta(client, &meta).await?;
+                Ok(Some(meta))
+            },
+            Some(meta) => Ok(Some(meta.clone())),
+            None => {
+                match Collection::vector_config(client, collection_name).await?.size {
+                    Some(dimension) => Ok(Some(Database::record_meta(client, collection_name, settings, model, dimension).await?)),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Creates the collection (and its metadata record, naming `model`) when it does not
+    /// exist yet and returns the new record, or `None` if the collection was already there.
+    /// Without a known `size` the dimension is probed from the embedding model.
+    pub async fn ensure_collection(client: &Qdrant, collection_name: &str, settings: &EmbeddingSettings, model: Option<String>, size: Option<u64>) -> Result<Option<CollectionMeta>, String> {
+        let exists = match client.collection_exists(collection_name).await {
+            Ok(exists) => exists,
+            Err(e) => return Err(e.to_string()),
+        };
+        if exists {
+            return Ok(None);
+        }
+
+        println!("There is no '{}' collection. Creating...", collection_name);
+        let size = match size {
+            Some(size) => size,
+            None => {
+                match Ollama::dimension(settings.model.clone()).await {
+                    Ok(size) => size,
+                    Err(_) => return Err(format!("Could not detect vector size of model '{}'", settings.model)),
+                }
+            }
+        };
+
+        let req = PostCollectionReq {
+            name: collection_name.to_string(),
+            ..Default::default()
+        };
+        Collection::create_collection(client, &req, size).await?;
+        let meta = Database::record_meta(client, collection_name, settings, model, size).await?;
+        Ok(Some(meta))
+    }
+
+    pub fn insert_options(meta: &Option<CollectionMeta>, id_strategy: Option<IdStrategy>, id_namespace_field: Option<String>, calculate_nearest: Option<usize>, chunking: Option<ChunkingOptions>) -> Result<InsertOptions, String> {
+        let id_strategy = id_strategy
+            .or(meta.as_ref().and_then(|m| m.id_strategy))
+            .unwrap_or_default();
+        let id_namespace_field = id_namespace_field
+            .or(meta.as_ref().and_then(|m| m.id_namespace_field.clone()));
+        if id_strategy == IdStrategy::Namespaced && id_namespace_field.is_none() {
+            return Err("The namespaced id strategy requires id_namespace_field".to_string());
+        }
+
+        Ok(InsertOptions {
+            calculate_nearest,
+            id_strategy,
+            id_namespace_field,
+            chunking,
+            dedupe_threshold: None,
+            dedupe_action: DedupeAction::default(),
+        })
+    }
+
+    /// Splits every item into chunk items that point back to their parent document.
+    /// Returns the chunks, their ids and the chunk count of each parent.
+    fn chunk_items(items: Vec<PostDatabaseItem>, ids: Vec<String>, options: &ChunkingOptions) -> (Vec<PostDatabaseItem>, Vec<String>, Vec<(String, usize)>) {
+        let mut chunk_items: Vec<PostDatabaseItem> = Vec::new();
+        let mut chunk_ids: Vec<String> = Vec::new();
+        let mut counts: Vec<(String, usize)> = Vec::new();
+
+        for (item, parent_id) in items.into_iter().zip(ids) {
+            let chunks = chunking::split(&item.text, options);
+            for (idx, chunk) in chunks.iter().enumerate() {
+                let mut metadata = item.metadata.clone().unwrap_or_default();
+                metadata.insert("parent_id".to_string(), json!(parent_id));
+                metadata.insert("chunk_index".to_string(), json!(idx));
+                metadata.insert("chunk_start".to_string(), json!(chunk.start));
+                metadata.insert("chunk_end".to_string(), json!(chunk.end));
+
+                let id = format!("{}#{}", parent_id, idx);
+                chunk_items.push(PostDatabaseItem {
+                    id: Some(id.clone()),
+                    text: chunk.text.clone(),
+                    metadata: Some(metadata),
+                });
+                chunk_ids.push(id);
+            }
+            counts.push((parent_id, chunks.len()));
+        }
+
+        (chunk_items, chunk_ids, counts)
+    }
+
+    /// Removes chunks left over from an earlier, longer version of a document, and the
+    /// whole-document point if the document was stored without chunking before.
+    async fn delete_stale_chunks(client: &Qdrant, collection_name: &str, parent_id: String, count: usize) -> Result<(), String> {
+        let filter = Filter::should([
+            Condition::has_id([PointId::from(Database::point_id(&parent_id))]),
+            Condition::from(Filter::must([
+                Condition::matches("parent_id", parent_id),
+                Condition::range("chunk_index", Range {
+                    gte: Some(count as f64),
+                    ..Default::default()
+                }),
+            ])),
+        ]);
+        match client.delete_points(DeletePointsBuilder::new(collection_name).points(filter).wait(true)).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Keeps the best hit of every parent document; hits are already sorted by score.
+    fn collapse_chunks(list: Vec<FindDatabaseResult>) -> Vec<FindDatabaseResult> {
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut collapsed: Vec<FindDatabaseResult> = Vec::new();
+        for mut item in list {
+            let parent_id = item.metadata.as_ref()
+                .and_then(|m| m.get("parent_id"))
+                .and_then(|v| v.as_str())
+                .map(|v| v.to_string());
+            if let Some(parent_id) = parent_id {
+                if !seen.insert(parent_id.clone()) {
+                    continue;
+                }
+                item.id = parent_id;
+            }
+            collapsed.push(item);
+        }
+        collapsed
+    }
+
+    /// Fetches the points already stored under `ids`, keyed by point id.
+    async fn existing_points(client: &Qdrant, collection_name: &str, ids: &[String]) -> Result<HashMap<String, RetrievedPoint>, String> {
+        let mut points: HashMap<String, RetrievedPoint> = HashMap::new();
+        let exists = client.collection_exists(collection_name).await.map_err(|e| e.to_string())?;
+        if !exists || ids.is_empty() {
+            return Ok(points);
+        }
+
+        let point_ids: Vec<PointId> = ids.iter().map(|id| PointId::from(Database::point_id(id))).collect();
+        let request = GetPointsBuilder::new(collection_name, point_ids)
+            .with_payload(true)
+            .with_vectors(true);
+        let response = client.get_points(request).await.map_err(|e| e.to_string())?;
+        for point in response.result {
+            points.insert(Database::point_id_to_string(point.id.clone()), point);
+        }
+
+        Ok(points)
+    }
+
+    /// Returns the stored vector (and translation) when the point was embedded
+    /// from the same content with the same model.
+    fn reusable_embedding(point: &RetrievedPoint, content_hash: &str, model: &str) -> Option<(Vec<f32>, Option<String>)> {
+        let stored_hash = point.payload.get("content_hash").and_then(|v| v.as_str())?;
+        let stored_model = point.payload.get("embedding_model").and_then(|v| v.as_str())?;
+        if stored_hash != content_hash || stored_model != model {
+            return None;
+        }
+
+        let english = point.payload.get("translated_document").and_then(|v| v.as_str().cloned());
+        let embeddings = Database::dense_vector(point.vectors.clone())?;
+        Some((embeddings, english))
+    }
+
+    async fn mid_distance(client: &Qdrant, collection_name: &str, config: VectorConfig, embeddings: &[f32], limit: usize) -> f32 {
+        let mut mid_distance: f32 = 0.0;
+        let nearests = Database::find_nearest(client, collection_name.to_string(), Some(embeddings.to_vec()), Some(limit), None, &NearestOptions::unfiltered(config)).await;
+        if let Ok(list) = nearests {
+            let mut len: f32 = 0.0;
+            for distance in list.into_iter().filter_map(|n| n.distance) {
+                mid_distance += distance;
+                len += 1.0;
+            }
+            if len > 0.0 {
+                mid_distance /= len;
+            }
+        }
+        mid_distance
+    }
+
+    async fn store_documents(client: &Qdrant, collection_name: &str, documents: &mut [StoredDocument], calculate_nearest: Option<usize>) -> Result<(), String> {
+        let config = Collection::vector_config(client, collection_name).await?;
+        if let Some(limit) = calculate_nearest {
+            for document in documents.iter_mut() {
+                let mid_distance = Database::mid_distance(client, collection_name, config, &document.embeddings, limit).await;
+                document.metadata.insert("mid_distance".to_string(), Value::from(mid_distance));
+            }
+        }
+
+        let points_to_upsert: Vec<PointStruct> = documents.iter().map(|document| Database::build_point(document, config.bm25)).collect();
+        if !points_to_upsert.is_empty() {
+            let upsert = client
+                .upsert_points(UpsertPointsBuilder::new(collection_name, points_to_upsert).wait(true))
+                .await;
+            if let Err(e) = upsert {
+                println!("{}", e);
+                return Err(e.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The nearest stored point closer than `threshold`, ignoring the document's own point.
+    async fn nearest_duplicate(client: &Qdrant, collection_name: &str, config: VectorConfig, document: &StoredDocument, threshold: f32) -> Option<FindDatabaseResult> {
+        let own_point_id = Database::point_id(&document.id);
+        let nearest = Database::find_nearest(client, collection_name.to_string(), Some(document.embeddings.clone()), Some(2), None, &NearestOptions::unfiltered(config)).await.ok()?;
+        nearest.into_iter()
+            .find(|n| n.point_id != own_point_id)
+            .filter(|n| n.distance.map(|d| d < threshold).unwrap_or(false))
+    }
+
+    /// Adds the keys the neighbour does not have yet; existing values are kept.
+    async fn merge_metadata(client: &Qdrant, collection_name: &str, nearest: &FindDatabaseResult, metadata: &Map<String, Value>) -> Result<(), String> {
+        const LOCAL_KEYS: &[&str] = &["parent_id", "chunk_index", "chunk_start", "chunk_end", "mid_distance", "duplicate_of"];
+
+        let existing = nearest.metadata.clone().unwrap_or_default();
+        let added: Map<String, Value> = metadata.iter()
+            .filter(|(key, _)| !existing.contains_key(*key) && !LOCAL_KEYS.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        if added.is_empty() {
+            return Ok(());
+        }
+
+        let request = SetPayloadPointsBuilder::new(collection_name, Payload::from(added))
+            .points_selector(vec![PointId::from(nearest.point_id.clone())])
+            .wait(true);
+        client.set_payload(request).await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Translates (when configured) and embeds `texts`, keeping their order. Calls to
+    /// Ollama run concurrently and embeddings are requested in batches.
+    pub async fn embed_texts(settings: &EmbeddingSettings, ids: &[String], texts: Vec<String>) -> Result<Vec<(Vec<f32>, Option<String>)>, String> {
+        let parallelism = Ollama::get_parallelism();
+
+        let englishes: Vec<Option<String>> = match &settings.translate_to {
+            Some(translate_to) => {
+                let prompts: Vec<String> = texts.iter().map(|text| translate_prompt(text.clone(), translate_to.clone())).collect();
+                let translated = bounded(prompts, parallelism, |prompt| Ollama::generate(prompt, settings.prompt_model.clone())).await;
+
+                let mut englishes: Vec<Option<String>> = Vec::new();
+                for (result, id) in translated.into_iter().zip(ids) {
+                    match result {
+                        Ok(english) => englishes.push(Some(english)),
+                        Err(_) => return Err(format!("Could not translate item '{}' with model '{}'", id, settings.prompt_model)),
+                    }
+                }
+                englishes
+            },
+            None => vec![None; texts.len()],
+        };
+
+        let inputs: Vec<String> = texts.into_iter().zip(&englishes)
+            .map(|(text, english)| english.clone().unwrap_or(text))
+            .collect();
+        let batch_size = Ollama::get_embedding_batch_size();
+        let batches: Vec<Vec<String>> = inputs.chunks(batch_size).map(|batch| batch.to_vec()).collect();
+        let embedded = bounded(batches, parallelism, |batch| Ollama::embeddings(batch, settings.model.clone())).await;
+
+        let mut embeddings: Vec<Vec<f32>> = Vec::new();
+        for (result, batch_ids) in embedded.into_iter().zip(ids.chunks(batch_size)) {
+            match result {
+                Ok(list) => embeddings.extend(list),
+                Err(_) => {
+                    let first = batch_ids.first().cloned().unwrap_or_default();
+                    let last = batch_ids.last().cloned().unwrap_or_default();
+                    return Err(format!("Could not embed items '{}' to '{}' with model '{}'", first, last, settings.model));
+                }
+            }
+        }
+
+        Ok(embeddings.into_iter().zip(englishes).collect())
+    }
+
+    /// The insert pipeline: derives ids, reuses vectors of unchanged documents,
+    /// translates and embeds the rest, then upserts everything. `meta` is updated to the
+    /// collection's record as it stands after the write.
+    pub async fn insert_items(client: &Qdrant, collection_name: &str, settings: &EmbeddingSettings, meta: &mut Option<CollectionMeta>, options: &InsertOptions, mut items: Vec<PostDatabaseItem>) -> Result<Vec<PostDatabaseResult>, String> {
+        let mut ids: Vec<String> = items.iter()
+            .map(|item| options.id_strategy.generate(item.id.as_ref(), &item.text, item.metadata.as_ref(), options.id_namespace_field.as_ref()))
+            .collect();
+
+        let mut chunk_counts: Vec<(String, usize)> = Vec::new();
+        if let Some(chunking) = &options.chunking {
+            let (chunk_items, chunk_ids, counts) = Database::chunk_items(items, ids, chunking);
+            items = chunk_items;
+            ids = chunk_ids;
+            chunk_counts = counts;
+        }
+
+        let existing = Database::existing_points(client, collection_name, &ids).await?;
+
+        let hashes: Vec<String> = items.iter()
+            .map(|item| content_hash(&item.text, settings.translate_to.as_deref()))
+            .collect();
+        let mut vectors: Vec<Option<(Vec<f32>, Option<String>)>> = ids.iter().zip(&hashes)
+            .map(|(id, hash)| existing
+                .get(&Database::point_id(id))
+                .and_then(|point| Database::reusable_embedding(point, hash, &settings.model)))
+            .collect();
+        let statuses: Vec<EmbeddingStatus> = vectors.iter()
+            .map(|v| if v.is_some() { EmbeddingStatus::Reused } else { EmbeddingStatus::Embedded })
+            .collect();
+
+        let pending: Vec<usize> = (0..items.len()).filter(|&idx| vectors[idx].is_none()).collect();
+        let pending_ids: Vec<String> = pending.iter().map(|&idx| ids[idx].clone()).collect();
+        let pending_texts: Vec<String> = pending.iter().map(|&idx| items[idx].text.clone()).collect();
+        let embedded = Database::embed_texts(settings, &pending_ids, pending_texts).await?;
+        for (idx, vector) in pending.into_iter().zip(embedded) {
+            vectors[idx] = Some(vector);
+        }
+
+        let mut documents: Vec<StoredDocument> = Vec::new();
+        for (((item, id), hash), vector) in items.into_iter().zip(ids).zip(hashes).zip(vectors) {
+            let (embeddings, english) = vector.unwrap_or_default();
+            documents.push(StoredDocument {
+                id,
+                text: item.text,
+                english,
+                embeddings,
+                metadata: item.metadata.unwrap_or_default(),
+                content_hash: Some(hash),
+                model: Some(settings.model.clone()),
+            });
+        }
+
+        let size = documents.first().map(|d| d.embeddings.len() as u64);
+        // A new collection's record is taken right away, so a failed write below does not lose it
+        let existed = match Database::ensure_collection(client, collection_name, settings, Some(settings.model.clone()), size).await? {
+            Some(created) => {
+                *meta = Some(created);
+                false
+            },
+            None => true,
+        };
+
+        let mut duplicates: Vec<Option<DuplicateResult>> = vec![None; documents.len()];
+        match options.dedupe_threshold {
+            // Documents are stored one at a time so later items also see earlier ones
+            Some(threshold) => {
+                let config = Collection::vector_config(client, collection_name).await?;
+                for (document, duplicate) in documents.iter_mut().zip(duplicates.iter_mut()) {
+                    if let Some(nearest) = Database::nearest_duplicate(client, collection_name, config, document, threshold).await {
+                        let duplicate_of = nearest.metadata.as_ref()
+                            .and_then(|m| m.get("duplicate_of"))
+                            .and_then(|v| v.as_str())
+                            .map(|v| v.to_string())
+                            .unwrap_or(nearest.id.clone());
+                        *duplicate = Some(DuplicateResult {
+                            action: options.dedupe_action,
+                            duplicate_of: duplicate_of.clone(),
+                            distance: nearest.distance.unwrap_or_default(),
+                        });
+
+                        match options.dedupe_action {
+                            DedupeAction::Skip => continue,
+                            DedupeAction::Merge => {
+                                Database::merge_metadata(client, collection_name, &nearest, &document.metadata).await?;
+                                continue;
+                            },
+                            DedupeAction::Link => {
+                                document.metadata.insert("duplicate_of".to_string(), json!(duplicate_of));
+                            }
+                        }
+                    }
+                    Database::store_documents(client, collection_name, std::slice::from_mut(document), options.calculate_nearest).await?;
+                }
+            },
+            None => Database::store_documents(client, collection_name, &mut documents, options.calculate_nearest).await?,
+        }
+        for (parent_id, count) in chunk_counts {
+            Database::delete_stale_chunks(client, collection_name, parent_id, count).await?;
+        }
+        if existed {
+            *meta = Database::record_first_write(client, collection_name, settings, meta, Some(settings.model.clone())).await?;
+        }
+
+        let results = documents.into_iter().zip(statuses).zip(duplicates)
+            .map(|((document, status), duplicate)| PostDatabaseResult {
+                text: document.text,
+                metadata: Some(document.metadata),
+                english: document.english,
+                embeddings: document.embeddings,
+                id: document.id,
+                embedding_status: status,
+                duplicate,
+            })
+            .collect();
+        Ok(results)
+    }
+
+	pub async fn insert(data: web::Json<PostDatabaseReq>) -> impl Responder {
+        let collection_name: String = data.collection.clone();
+        let client = Database::connect();
+        let (settings, mut meta) = match Database::load_settings(&client, &collection_name, data.model.clone(), data.translate_to.clone()).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+
+        let options = match Database::insert_options(&meta, data.id_strategy, data.id_namespace_field.clone(), data.calculate_nearest, data.chunking.clone()) {
+            Ok(options) => options.with_dedupe(data.dedupe_threshold, data.dedupe_action),
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        match Database::insert_items(&client, &collection_name, &settings, &mut meta, &options, data.data.items()).await {
+            Ok(results) => {
+                HttpResponse::Ok().json(GeneralValueResult{result: results, status: true})
+            },
+            Err(e) => {
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)})
+            }
+        }
+	} 
+
+    /// Default chunking of an uploaded file; `chunk_mode=none` stores it whole.
+    fn upload_chunking(kind: FileKind, mode: Option<&String>, size: Option<usize>, overlap: Option<usize>) -> Result<Option<ChunkingOptions>, String> {
+        let mode = match mode.map(|m| m.as_str()) {
+            Some("none") => return Ok(None),
+            Some(mode) => serde_json::from_value::<ChunkMode>(json!(mode)).map_err(|_| format!("Unknown chunk_mode '{}'", mode))?,
+            None if kind == FileKind::Markdown => ChunkMode::Markdown,
+            None => ChunkMode::Paragraphs,
+        };
+        Ok(Some(ChunkingOptions { mode, size, overlap }))
+    }
+
+    /// Reads every part of a multipart body: parts with a filename are files, the rest are text fields.
+    async fn read_multipart(payload: &mut Multipart) -> Result<(HashMap<String, String>, Vec<UploadedFile>), HttpResponse> {
+        let max_bytes = Database::get_upload_max_bytes();
+        let max_total_bytes = Database::get_upload_max_total_bytes();
+        let mut total_bytes: usize = 0;
+        let mut fields: HashMap<String, String> = HashMap::new();
+        let mut files: Vec<UploadedFile> = Vec::new();
+
+        while let Some(field) = payload.next().await {
+            let mut field = match field {
+                Ok(field) => field,
+                Err(e) => {
+                    return Err(HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e.to_string())}));
+                }
+            };
+            let name = field.name().unwrap_or_default().to_string();
+            let filename = field.content_disposition().and_then(|cd| cd.get_filename()).map(|f| f.to_string());
+            let mime_type = field.content_type().map(|m| m.essence_str().to_string());
+
+            let mut bytes: Vec<u8> = Vec::new();
+            while let Some(chunk) = field.next().await {
+                match chunk {
+                    Ok(chunk) => {
+                        total_bytes += chunk.len();
+                        bytes.extend_from_slice(&chunk);
+                    },
+                    Err(e) => {
+                        return Err(HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e.to_string())}));
+                    }
+                }
+                if bytes.len() > max_bytes {
+                    let message = format!("Part '{}' is larger than {} bytes", filename.unwrap_or(name), max_bytes);
+                    return Err(HttpResponse::PayloadTooLarge().json(ErrorResult {status: false, message: Some(message)}));
+                }
+                if total_bytes > max_total_bytes {
+                    let message = format!("The upload is larger than {} bytes in total", max_total_bytes);
+                    return Err(HttpResponse::PayloadTooLarge().json(ErrorResult {status: false, message: Some(message)}));
+                }
+            }
+
+            match filename {
+                Some(filename) => files.push(UploadedFile {
+                    filename,
+                    mime_type: mime_type.unwrap_or_default(),
+                    bytes,
+                }),
+                None => {
+                    fields.insert(name, String::from_utf8_lossy(&bytes).to_string());
+                }
+            }
+        }
+
+        Ok((fields, files))
+    }
+
+    /// Multipart ingestion: every part with a filename is a document, the other
+    /// parts (`collection`, `model`, `translate_to`, `chunk_mode`, `chunk_size`,
+    /// `chunk_overlap`, `metadata`) configure the insert.
+	pub async fn upload(mut payload: Multipart) -> impl Responder {
+        let (fields, files) = match Database::read_multipart(&mut payload).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+
+        let collection_name = match fields.get("collection") {
+            Some(collection) => collection.clone(),
+            None => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("Missing 'collection' field".to_string())});
+            }
+        };
+        if files.is_empty() {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("No files were uploaded".to_string())});
+        }
+
+        let metadata: Map<String, Value> = match fields.get("metadata") {
+            Some(raw) => match serde_json::from_str(raw) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(format!("Invalid metadata: {}", e))});
+                }
+            },
+            None => Map::new(),
+        };
+        let chunk_size = fields.get("chunk_size").and_then(|v| v.trim().parse::<usize>().ok());
+        let chunk_overlap = fields.get("chunk_overlap").and_then(|v| v.trim().parse::<usize>().ok());
+
+        let client = Database::connect();
+        let (settings, meta) = match Database::load_settings(&client, &collection_name, fields.get("model").cloned(), fields.get("translate_to").cloned()).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+
+        // Every file is read before any is stored, so a bad file does not leave a partial upload
+        let mut extracted: Vec<(UploadedFile, String, InsertOptions)> = Vec::new();
+        for file in files {
+            let filename = file.filename.clone();
+            let kind = match FileKind::detect(&filename, Some(&file.mime_type)) {
+                Some(kind) => kind,
+                None => {
+                    let message = format!("Unsupported file type of '{}'", filename);
+                    return HttpResponse::UnsupportedMediaType().json(ErrorResult {status: false, message: Some(message)});
+                }
+            };
+            let text = match extract::extract_text(kind, &file.bytes) {
+                Ok(text) => text,
+                Err(e) => {
+                    let message = format!("Could not read '{}': {}", filename, e);
+                    return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(message)});
+                }
+            };
+            if text.trim().is_empty() {
+                let message = format!("No text found in '{}'", filename);
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(message)});
+            }
+
+            let chunking = match Database::upload_chunking(kind, fields.get("chunk_mode"), chunk_size, chunk_overlap) {
+                Ok(chunking) => chunking,
+                Err(e) => {
+                    return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+                }
+            };
+            let options = match Database::insert_options(&meta, None, None, None, chunking) {
+                Ok(options) => options,
+                Err(e) => {
+                    return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+                }
+            };
+            extracted.push((file, text, options));
+        }
+
+        let mut meta = meta;
+        let mut results: Vec<UploadDatabaseResult> = Vec::new();
+        for (UploadedFile { filename, mime_type, bytes }, text, options) in extracted {
+            let mut file_metadata = metadata.clone();
+            file_metadata.insert("filename".to_string(), json!(filename));
+            file_metadata.insert("mime_type".to_string(), json!(mime_type));
+            file_metadata.insert("size".to_string(), json!(bytes.len()));
+            let id = options.id_strategy.generate(Some(&filename), &text, Some(&file_metadata), options.id_namespace_field.as_ref());
+            let item = PostDatabaseItem {
+                id: Some(id.clone()),
+                text,
+                metadata: Some(file_metadata),
+            };
+
+            // The id is generated here so the response names the stored document
+            let insert_options = InsertOptions {
+                id_strategy: IdStrategy::Provided,
+                ..options
+            };
+            match Database::insert_items(&client, &collection_name, &settings, &mut meta, &insert_options, vec![item]).await {
+                Ok(items) => results.push(UploadDatabaseResult {
+                    id,
+                    filename,
+                    mime_type,
+                    size: bytes.len(),
+                    items,
+                }),
+                Err(e) => {
+                    // Name the files already stored so the client knows what to retry
+                    let stored: Vec<&str> = results.iter().map(|r| r.filename.as_str()).collect();
+                    let message = format!("Could not store '{}': {} (already stored: {:?})", filename, e, stored);
+                    return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(message)});
+                }
+            }
+        }
+
+        HttpResponse::Ok().json(GeneralValueResult{result: results, status: true})
+	}
+
+    /// Inserts one batch of CSV rows, recording a failure for every row when it fails.
+    async fn insert_csv_batch(client: &Qdrant, collection_name: &str, settings: &EmbeddingSettings, meta: &mut Option<CollectionMeta>, options: &InsertOptions, batch: Vec<(u64, PostDatabaseItem)>, result: &mut CsvImportResult) {
+        let (lines, items): (Vec<u64>, Vec<PostDatabaseItem>) = batch.into_iter().unzip();
+        match Database::insert_items(client, collection_name, settings, meta, options, items).await {
+            Ok(_) => result.inserted += lines.len(),
+            Err(e) => {
+                result.failed += lines.len();
+                result.errors.extend(lines.into_iter().map(|line| CsvRowError { line, message: e.clone() }));
+            }
+        }
+    }
+
+    /// CSV import: a file part plus a `mapping` field (see `CsvMapping`) and the
+    /// optional `model`, `translate_to` and `batch_size` fields.
+	pub async fn import_csv(path: web::Path<String>, mut payload: Multipart) -> impl Responder {
+        let collection_name = path.into_inner();
+        let (fields, files) = match Database::read_multipart(&mut payload).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+
+        let file = match files.into_iter().next() {
+            Some(file) => file,
+            None => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("No CSV file was uploaded".to_string())});
+            }
+        };
+        let mapping: CsvMapping = match fields.get("mapping").map(|raw| serde_json::from_str(raw)) {
+            Some(Ok(mapping)) => mapping,
+            Some(Err(e)) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(format!("Invalid mapping: {}", e))});
+            },
+            None => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("Missing 'mapping' field".to_string())});
+            }
+        };
+
+        let delimiter = match mapping.delimiter_byte() {
+            Ok(delimiter) => delimiter,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(file.bytes.as_slice());
+        let headers: Vec<String> = match reader.headers() {
+            Ok(headers) => headers.iter().map(|h| h.trim().to_string()).collect(),
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(format!("Could not read CSV header: {}", e))});
+            }
+        };
+        if let Err(e) = mapping.validate(&headers) {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+        }
+
+        let client = Database::connect();
+        let (settings, mut meta) = match Database::load_settings(&client, &collection_name, fields.get("model").cloned(), fields.get("translate_to").cloned()).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+        let options = match Database::insert_options(&meta, None, None, None, None) {
+            Ok(options) => options,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+        let batch_size = fields.get("batch_size")
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_IMPORT_BATCH_SIZE)
+            .clamp(1, MAX_IMPORT_BATCH_SIZE);
+
+        let mut result = CsvImportResult { rows: 0, inserted: 0, failed: 0, errors: Vec::new() };
+        let mut batch: Vec<(u64, PostDatabaseItem)> = Vec::new();
+        for record in reader.records() {
+            result.rows += 1;
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    let line = e.position().map(|p| p.line()).unwrap_or(0);
+                    result.failed += 1;
+                    result.errors.push(CsvRowError { line, message: e.to_string() });
+                    continue;
+                }
+            };
+            let line = record.position().map(|p| p.line()).unwrap_or(0);
+            let row: HashMap<&str, &str> = headers.iter().map(|h| h.as_str()).zip(record.iter()).collect();
+
+            match mapping.map_row(&row) {
+                Ok(mapped) => batch.push((line, PostDatabaseItem {
+                    id: mapped.id,
+                    text: mapped.text,
+                    metadata: Some(mapped.metadata),
+                })),
+                Err(message) => {
+                    result.failed += 1;
+                    result.errors.push(CsvRowError { line, message });
+                }
+            }
+
+            if batch.len() >= batch_size {
+                Database::insert_csv_batch(&client, &collection_name, &settings, &mut meta, &options, std::mem::take(&mut batch), &mut result).await;
+            }
+        }
+        if !batch.is_empty() {
+            Database::insert_csv_batch(&client, &collection_name, &settings, &mut meta, &options, batch, &mut result).await;
+        }
+
+        HttpResponse::Ok().json(GeneralValueResult{result, status: true})
+	}
+
+    fn check_dimensions(collection_name: &str, expected_size: Option<u64>, items: &[PostEmbeddingsItem]) -> Result<(), String> {
+        let expected_size = expected_size.or(items.first().map(|item| item.embeddings.len() as u64));
+        if let Some(expected_size) = expected_size {
+            if let Some(item) = items.iter().find(|item| item.embeddings.len() as u64 != expected_size) {
+                return Err(format!("Item '{}' has {} dimensions, but collection '{}' expects {}", item.id, item.embeddings.len(), collection_name, expected_size));
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores precomputed embeddings; dimensions must already be checked. `model` is
+    /// only recorded when the caller named the model the vectors came from. Like
+    /// `insert_items`, it leaves the current record in `meta`.
+    async fn insert_embedding_items(client: &Qdrant, collection_name: &str, settings: &EmbeddingSettings, meta: &mut Option<CollectionMeta>, model: Option<String>, items: Vec<PostEmbeddingsItem>, calculate_nearest: Option<usize>) -> Result<Vec<String>, String> {
+        let size = items.first().map(|item| item.embeddings.len() as u64);
+        let existed = match Database::ensure_collection(client, collection_name, settings, model.clone(), size).await? {
+            Some(created) => {
+                *meta = Some(created);
+                false
+            },
+            None => true,
+        };
+
+        let ids: Vec<String> = items.iter().map(|item| item.id.clone()).collect();
+        let mut documents: Vec<StoredDocument> = items.into_iter()
+            .map(|item| StoredDocument {
+                content_hash: Some(content_hash(&item.text, None)),
+                model: model.clone(),
+                id: item.id,
+                text: item.text,
+                english: item.english,
+                embeddings: item.embeddings,
+                metadata: item.metadata.unwrap_or_default(),
+            })
+            .collect();
+
+        Database::store_documents(client, collection_name, &mut documents, calculate_nearest).await?;
+        if existed {
+            *meta = Database::record_first_write(client, collection_name, settings, meta, model).await?;
+        }
+        Ok(ids)
+    }
+
+	pub async fn insert_embeddings(data: web::Json<PostEmbeddingsReq>) -> impl Responder {
+        let collection_name: String = data.collection.clone();
+        let client = Database::connect();
+        let (settings, mut meta) = match Database::load_settings(&client, &collection_name, data.model.clone(), None).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+
+        if let Err(e) = Database::check_dimensions(&collection_name, meta.as_ref().map(|m| m.dimension), &data.data) {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+        }
+
+        match Database::insert_embedding_items(&client, &collection_name, &settings, &mut meta, data.model.clone(), data.data.clone(), data.calculate_nearest).await {
+            Ok(result) => HttpResponse::Ok().json(GeneralValueResult{result, status: true}),
+            Err(e) => HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)}),
+        }
+	} 
+
+    /// Bulk import from an NDJSON body, one `PostDatabaseItem` or `PostEmbeddingsItem`
+    /// per line. Progress and per-line errors are streamed back as NDJSON events.
+	pub async fn import(path: web::Path<String>, query: web::Query<ImportDatabaseQuery>, body: web::Payload) -> impl Responder {
+        let collection_name = path.into_inner();
+        let client = Database::connect();
+        let (settings, meta) = match Database::load_settings(&client, &collection_name, query.model.clone(), query.translate_to.clone()).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+
+        let options = match Database::insert_options(&meta, query.id_strategy, query.id_namespace_field.clone(), query.calculate_nearest, None) {
+            Ok(options) => options.with_dedupe(query.dedupe_threshold, query.dedupe_action),
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        let (events, stream) = mpsc::channel::<Result<web::Bytes, Error>>(16);
+        let import = NdjsonImport {
+            client,
+            collection_name,
+            settings,
+            meta,
+            options,
+            model: query.model.clone(),
+            batch_size: query.batch_size.unwrap_or(DEFAULT_IMPORT_BATCH_SIZE).clamp(1, MAX_IMPORT_BATCH_SIZE),
+            items: Vec::new(),
+            embeddings: Vec::new(),
+            lines: 0,
+            inserted: 0,
+            failed: 0,
+            events,
+        };
+        rt::spawn(import.run(body));
+
+        HttpResponse::Ok()
+            .content_type("application/x-ndjson")
+            .streaming(stream)
+	}
+
+    fn filter_number(filter: &FindDatabaseFilterReq) -> Result<f64, String> {
+        match filter.value.as_f64() {
+            Some(v) => Ok(v),
+            None => Err(format!("Filter on '{}' expects a number", filter.field)),
+        }
+    }
+
+    /// Builds a Qdrant filter from the `find` filter list; `point_ids` are raw Qdrant point ids.
+    pub fn build_filter(filters: Option<Vec<FindDatabaseFilterReq>>, point_ids: Option<Vec<String>>) -> Result<Filter, String> {
+        Database::build_filter_with(filters, point_ids, false)
+    }
+
+    /// Same as `build_filter`, but string `=` matches the whole keyword instead of any text
+    /// containing it. Used by delete and patch, where a substring hit would touch unrelated items.
+    /// `ids` are user-facing ids and also match the chunks stored under them.
+    pub fn build_exact_filter(filters: Option<Vec<FindDatabaseFilterReq>>, ids: Option<Vec<String>>) -> Result<Filter, String> {
+        let mut filter = Database::build_filter_with(filters, None, true)?;
+        if let Some(ids) = ids {
+            if !ids.is_empty() {
+                filter.must.push(Database::ids_condition(ids));
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Matches the points stored under `ids` and the chunks whose parent is one of them.
+    fn ids_condition(ids: Vec<String>) -> Condition {
+        let point_ids: Vec<PointId> = ids.iter().map(|id| PointId::from(Database::point_id(id))).collect();
+        Condition::from(Filter::should([
+            Condition::has_id(point_ids),
+            Condition::matches("parent_id", ids),
+        ]))
+    }
+
+    fn build_filter_with(filters: Option<Vec<FindDatabaseFilterReq>>, point_ids: Option<Vec<String>>, exact: bool) -> Result<Filter, String> {
+        let mut filter_conditions: Vec<Condition> = Vec::new();
+
+        if let Some(list) = filters {
+            for l in list {
+                match l.operator.as_str() {
+                    ">" => {
+                        filter_conditions.push(Condition::range(
+                            l.field.clone(),
+                            Range {
+                                gt: Some(Database::filter_number(&l)?),
+                                ..Default::default()
+                            },
+                        ));
+                    },
+                    ">=" => {
+                        filter_conditions.push(Condition::range(
+                            l.field.clone(),
+                            Range {
+                                gte: Some(Database::filter_number(&l)?),
+                                ..Default::default()
+                            },
+                        ));
+                    },
+                    "<" => {
+                        filter_conditions.push(Condition::range(
+                            l.field.clone(),
+                            Range {
+                                lt: Some(Database::filter_number(&l)?),
+                                ..Default::default()
+                            },
+                        ));
+                    },
+                    "<=" => {
+                        filter_conditions.push(Condition::range(
+                            l.field.clone(),
+                            Range {
+                                lte: Some(Database::filter_number(&l)?),
+                                ..Default::default()
+                            },
+                        ));
+                    },
+                    "=" => {
+                        match l.value.as_i64() {
+                            Some(v) => {
+                                filter_conditions.push(Condition::matches(
+                                    l.field,
+                                    v,
+                                ));
+                            },
+                            None => {
+                                let text = match l.value.as_str() {
+                                    Some(text) => text.to_string(),
+                                    None => return Err(format!("Filter on '{}' expects a number or a string", l.field)),
+                                };
+                                if exact {
+                                    filter_conditions.push(Condition::matches(
+                                        l.field,
+                                        text,
+                                    ));
+                                } else {
+                                    filter_conditions.push(Condition::matches_text(
+                                        l.field,
+                                        text,
+                                    ));
+                                }
+                            }
+                        }
+                    },
+                    _ => {
+                        return Err(format!("Unknown filter operator '{}'", l.operator));
+                    }
+                }
+            }
+        }
+        
+        if let Some(ids) = point_ids {
+            if !ids.is_empty() {
+                let point_ids: Vec<PointId> = ids.into_iter().map(PointId::from).collect();
+                filter_conditions.push(Condition::has_id(point_ids));
+            }
+        }
+
+        Ok(Filter::must(filter_conditions))
+    }
+
+    fn scored_to_result(scored_point: ScoredPoint, distance: Option<f32>) -> FindDatabaseResult {
+        let mut payload = scored_point.payload;
+        let vector = Database::dense_vector(scored_point.vectors);
+
+        let text = Database::take_document(&mut payload);
+
+        let point_id = Database::point_id_to_string(scored_point.id);
+        let id = Database::take_original_id(&mut payload, &point_id);
+
+        let metadata = payload;
+
+        FindDatabaseResult {
+            id,
+            point_id,
+            metadata: Some(Database::take_metadata(metadata)),
+            text,
+            distance,
+            score: None,
+            rerank_score: None,
+            vector,
+        }
+    }
+
+    async fn find_nearest(client: &Qdrant, collection_name: String, embedding: Option<Vec<f32>>, limit: Option<usize>, offset: Option<usize>, options: &NearestOptions) -> Result<Vec<FindDatabaseResult>, bool> {
+        let point_ids = options.filter_by_ids.as_ref().map(|ids| ids.iter().map(|id| Database::point_id(id)).collect());
+        let filter = match Database::build_filter(options.filters.clone(), point_ids) {
+            Ok(filter) => filter,
+            Err(e) => {
+                println!("{}", e);
+                return Err(false);
+            }
+        };
+
+        // Without a query vector the points come back unranked and have no distance
+        let has_query = embedding.is_some();
+        let mut search_request = QueryPointsBuilder::new(collection_name);
+        if let Some(embd) = embedding {
+            search_request = search_request.query(embd);
+        }
+
+        if let Some(score_threshold) = options.score_threshold {
+            search_request = search_request.score_threshold(score_threshold);
+        }
+
+        search_request = search_request.filter(filter)
+            .limit(limit.unwrap_or(10) as u64)
+            .offset(offset.unwrap_or(0) as u64)
+            .with_payload(true)
+            .with_vectors(true);
+
+        let search_result = client.query(search_request).await;
+
+        match search_result {
+            Ok(response) => {
+                Ok(response.result.into_iter()
+                    .map(|scored_point| {
+                        let distance = has_query.then(|| options.config.distance_of(scored_point.score));
+                        Database::scored_to_result(scored_point, distance)
+                    })
+                    .collect())
+            },
+            Err(e) => {
+                println!("{}", e);
+                Err(false)
+            }
+        }
+    }
+
+    /// Keyword ranking over the BM25 sparse vector; `score` holds the raw BM25 score.
+    /// BM25 scores are unbounded, so `distance` is left empty for the caller to fill.
+    async fn find_sparse(client: &Qdrant, collection_name: String, text: &str, limit: usize, filters: Option<Vec<FindDatabaseFilterReq>>, filter_by_ids: Option<Vec<String>>) -> Result<Vec<FindDatabaseResult>, bool> {
+        let (indices, values) = bm25::query_vector(text);
+        if indices.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let point_ids = filter_by_ids.map(|ids| ids.iter().map(|id| Database::point_id(id)).collect());
+        let filter = Database::build_filter(filters, point_ids).map_err(|_| false)?;
+
+        let search_request = QueryPointsBuilder::new(collection_name)
+            .query(VectorInput::new_sparse(indices, values))
+            .using(SPARSE_VECTOR_NAME)
+            .filter(filter)
+            .limit(limit as u64)
+            .with_payload(true)
+            .with_vectors(true);
+
+        match client.query(search_request).await {
+            Ok(response) => {
+                Ok(response.result.into_iter()
+                    .map(|scored_point| {
+                        let score = scored_point.score;
+                        FindDatabaseResult {
+                            score: Some(score),
+                            ..Database::scored_to_result(scored_point, None)
+                        }
+                    })
+                    .collect())
+            },
+            Err(e) => {
+                println!("{}", e);
+                Err(false)
+            }
+        }
+    }
+
+    fn normalized_scores(scores: &[f32]) -> Vec<f32> {
+        let min = scores.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max = scores.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        scores.iter()
+            .map(|score| if max > min { (score - min) / (max - min) } else { 1.0 })
+            .collect()
+    }
+
+    /// Fuses the dense and sparse rankings into one list sorted by fused `score`.
+    fn fuse(dense: Vec<FindDatabaseResult>, sparse: Vec<FindDatabaseResult>, fusion: FusionMethod, dense_weight: f32) -> Vec<FindDatabaseResult> {
+        let dense_scores: Vec<f32> = match fusion {
+            FusionMethod::Rrf => (0..dense.len()).map(|rank| 1.0 / (RRF_K + rank as f32 + 1.0)).collect(),
+            FusionMethod::Weighted => Database::normalized_scores(&dense.iter().map(|d| 1.0 - d.distance.unwrap_or_default()).collect::<Vec<f32>>()),
+        };
+        let sparse_scores: Vec<f32> = match fusion {
+            FusionMethod::Rrf => (0..sparse.len()).map(|rank| 1.0 / (RRF_K + rank as f32 + 1.0)).collect(),
+            FusionMethod::Weighted => Database::normalized_scores(&sparse.iter().map(|s| s.score.unwrap_or(0.0)).collect::<Vec<f32>>()),
+        };
+
+        let mut fused: Vec<FindDatabaseResult> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for (item, score) in dense.into_iter().zip(dense_scores) {
+            positions.insert(item.point_id.clone(), fused.len());
+            fused.push(FindDatabaseResult {
+                score: Some(dense_weight * score),
+                ..item
+            });
+        }
+        for (item, score) in sparse.into_iter().zip(sparse_scores) {
+            let weighted = (1.0 - dense_weight) * score;
+            match positions.get(&item.point_id) {
+                Some(&pos) => {
+                    fused[pos].score = Some(fused[pos].score.unwrap_or(0.0) + weighted);
+                },
+                None => {
+                    // Keyword-only hits get their dense distance filled in afterwards
+                    positions.insert(item.point_id.clone(), fused.len());
+                    fused.push(FindDatabaseResult {
+                        score: Some(weighted),
+                        ..item
+                    });
+                }
+            }
+        }
+
+        fused.sort_by(|a, b| b.score.unwrap_or(0.0).total_cmp(&a.score.unwrap_or(0.0)));
+        fused
+    }
+
+    /// Grades every candidate against the query and sorts by the grade. Candidates the
+    /// model could not grade keep their order behind the graded ones.
+    async fn rerank(query: &str, list: Vec<FindDatabaseResult>, model: String) -> Vec<FindDatabaseResult> {
+        let mut graded = bounded(list, Ollama::get_parallelism(), |item| {
+            let prompt = rerank_prompt(query, &item.text);
+            let model = model.clone();
+            async move {
+                let rerank_score = Ollama::generate(prompt, model).await.ok().and_then(|reply| parse_rerank_grade(&reply));
+                FindDatabaseResult { rerank_score, ..item }
+            }
+        }).await;
+
+        graded.sort_by(|a, b| match (a.rerank_score, b.rerank_score) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+        graded
+    }
+
+    /// Keeps `count` candidates picked by maximal marginal relevance. Relevance is the
+    /// rerank score when there is one, otherwise the similarity to the query vector.
+    fn diversify(list: Vec<FindDatabaseResult>, query: &[f32], diversity: f32, count: usize) -> Vec<FindDatabaseResult> {
+        let vectors: Vec<Vec<f32>> = list.iter().map(|item| item.vector.clone().unwrap_or_default()).collect();
+        let relevance: Vec<f32> = list.iter().zip(&vectors)
+            .map(|(item, vector)| item.rerank_score.unwrap_or_else(|| mmr::cosine_similarity(query, vector)))
+            .collect();
+
+        let picked = mmr::select(&relevance, &vectors, count, 1.0 - diversity);
+        let mut slots: Vec<Option<FindDatabaseResult>> = list.into_iter().map(Some).collect();
+        picked.into_iter().filter_map(|idx| slots[idx].take()).collect()
+    }
+
+    async fn find_hybrid(client: &Qdrant, collection_name: String, text: &str, embedding: Vec<f32>, data: &FindDatabaseReq, options: &NearestOptions, pool: usize) -> Result<Vec<FindDatabaseResult>, bool> {
+        let dense = Database::find_nearest(client, collection_name.clone(), Some(embedding.clone()), Some(pool), None, options).await?;
+        let sparse = Database::find_sparse(client, collection_name.clone(), text, pool, options.filters.clone(), options.filter_by_ids.clone()).await?;
+
+        let dense_weight = data.dense_weight.unwrap_or(0.5).clamp(0.0, 1.0);
+        let mut fused = Database::fuse(dense, sparse, data.fusion.unwrap_or_default(), dense_weight);
+
+        let missing: Vec<String> = fused.iter().filter(|item| item.distance.is_none()).map(|item| item.id.clone()).collect();
+        if !missing.is_empty() {
+            let count = missing.len();
+            let fill_options = NearestOptions {
+                filters: None,
+                filter_by_ids: Some(missing),
+                ..options.clone()
+            };
+            let distances: HashMap<String, Option<f32>> = Database::find_nearest(client, collection_name, Some(embedding), Some(count), None, &fill_options).await?
+                .into_iter()
+                .map(|item| (item.point_id, item.distance))
+                .collect();
+            for item in fused.iter_mut().filter(|item| item.distance.is_none()) {
+                item.distance = distances.get(&item.point_id).cloned().flatten();
+            }
+            // Keyword hits outside the dense cutoff are dropped
+            if options.score_threshold.is_some() {
+                fused.retain(|item| item.distance.is_some());
+            }
+        }
+
+        Ok(fused)
+    }
+
+	pub async fn find(data: web::Json<FindDatabaseReq>) -> impl Responder {
+        let mut data = data.into_inner();
         let collection_name: String = data.collection.clone();
-        let nearests = Database::find_nearest(collection_name, embedding, data.limit, data.offset, data.filters.clone(), data.filter_by_ids.clone()).await;
+        let client = Database::connect();
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+        let (settings, _) = match Database::load_settings(&client, &collection_name, data.model.clone(), data.translate_to.clone()).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+        let model = settings.model.clone();
+        let mode = data.mode.unwrap_or_default();
+
+        // Keyword mode falls back to the query text when no keywords are given
+        let keywords = match mode {
+            SearchMode::Keyword => data.keywords.clone().or(data.text.clone()),
+            _ => data.keywords.clone(),
+        }.filter(|k| !k.trim().is_empty());
+        if mode == SearchMode::Keyword && keywords.is_none() {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("Keyword search needs keywords or a text".to_string())});
+        }
+        if let Some(keywords) = &keywords {
+            data.filters.get_or_insert_with(Vec::new).push(FindDatabaseFilterReq {
+                field: TEXT_INDEX_FIELD.to_string(),
+                value: json!(keywords),
+                operator: "=".to_string(),
+            });
+        }
+
+        // Reranking and diversification fetch a candidate pool first and page over the reordered list
+        let page_limit = data.limit.unwrap_or(10);
+        let page_offset = data.offset.unwrap_or(0);
+        let diversity = data.diversity.map(|d| d.clamp(0.0, 1.0));
+        let mut pool = data.rerank_top_n.map(|top_n| top_n.max(page_limit + page_offset));
+        if diversity.is_some() {
+            let candidates = data.candidate_pool.unwrap_or((page_limit + page_offset) * 4).max(page_limit + page_offset);
+            pool = Some(pool.map_or(candidates, |p| p.max(candidates)));
+        }
+        let rerank_query = data.text.clone().or(keywords.clone());
+        if data.rerank_top_n.is_some() && rerank_query.is_none() {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("Reranking needs a text".to_string())});
+        }
+        if let Some(pool) = pool {
+            data.limit = Some(pool);
+            data.offset = Some(0);
+        }
+
+        let query_text = if mode == SearchMode::Keyword { None } else { data.text.clone() };
+        let embedding: Option<Vec<f32>> = match &query_text {
+            Some(text) => {
+                let mut input = text.clone();
+                if let Some(translate_to) = &settings.translate_to {
+                    let prompt = translate_prompt(text.clone(), translate_to.clone());
+                    input = match Ollama::generate(prompt, settings.prompt_model.clone()).await {
+                        Ok(english) => english,
+                        Err(_) => {
+                            let message = format!("Could not translate the query with model '{}'", settings.prompt_model);
+                            return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(message)});
+                        }
+                    };
+                }
+                match Ollama::embedding(input, model.clone()).await {
+                    Ok(embeddings) => Some(embeddings),
+                    Err(_) => {
+                        let message = format!("Could not embed the query with model '{}'", model);
+                        return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(message)});
+                    }
+                }
+            },
+            None => None,
+        };
+
+        if let Err(e) = Database::build_filter(data.filters.clone(), None) {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+        }
+        if diversity.is_some() && embedding.is_none() {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("Diversification needs a semantic query text".to_string())});
+        }
+        let query_vector = if diversity.is_some() { embedding.clone() } else { None };
+
+        let config = match Collection::vector_config(&client, &collection_name).await {
+            Ok(config) => config,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+        let score_threshold = match config.score_threshold(data.min_score, data.max_distance) {
+            Ok(threshold) => threshold,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+        if score_threshold.is_some() && embedding.is_none() {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("max_distance and min_score need a semantic query text".to_string())});
+        }
+        let options = NearestOptions {
+            config,
+            filters: data.filters.clone(),
+            filter_by_ids: data.filter_by_ids.clone(),
+            score_threshold,
+        };
+        if keywords.is_some() {
+            match Collection::has_text_index(&client, &collection_name).await {
+                Ok(true) => {},
+                Ok(false) => {
+                    let message = format!("Collection '{}' has no text index; add one with PUT /api/v1/collections/{}/text-index", collection_name, collection_name);
+                    return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(message)});
+                },
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)});
+                }
+            }
+        }
+
+        let collapse = data.collapse.unwrap_or(false);
+        let nearests = if mode == SearchMode::Keyword {
+            let limit = data.limit.unwrap_or(10);
+            let offset = data.offset.unwrap_or(0);
+            let pool = if collapse { (limit + offset) * 4 } else { limit + offset };
+            let found = match (config.bm25, &keywords) {
+                (true, Some(keywords)) => Database::find_sparse(&client, collection_name, keywords, pool, data.filters.clone(), data.filter_by_ids.clone()).await,
+                _ => Database::find_nearest(&client, collection_name, None, Some(pool), None, &options).await,
+            };
+            found
+                .map(|list| if collapse { Database::collapse_chunks(list) } else { list })
+                .map(|list| list.into_iter().skip(offset).take(limit).collect::<Vec<FindDatabaseResult>>())
+        } else if mode == SearchMode::Hybrid {
+            let (text, embedding) = match (&data.text, embedding) {
+                (Some(text), Some(embedding)) => (text.clone(), embedding),
+                _ => {
+                    return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("Hybrid search needs a text".to_string())});
+                }
+            };
+            if !config.bm25 {
+                let message = format!("Collection '{}' has no BM25 index; recreate it to use hybrid search", collection_name);
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(message)});
+            }
+
+            // Both rankings are over-fetched so fusion has candidates to reorder
+            let limit = data.limit.unwrap_or(10);
+            let offset = data.offset.unwrap_or(0);
+            Database::find_hybrid(&client, collection_name, &text, embedding, &data, &options, (limit + offset) * 4).await
+                .map(|list| if collapse { Database::collapse_chunks(list) } else { list })
+                .map(|list| list.into_iter().skip(offset).take(limit).collect::<Vec<FindDatabaseResult>>())
+        } else if collapse {
+            // Over-fetch so enough distinct parents remain after collapsing
+            let limit = data.limit.unwrap_or(10);
+            let offset = data.offset.unwrap_or(0);
+            Database::find_nearest(&client, collection_name, embedding, Some((limit + offset) * 4), None, &options).await
+                .map(|list| Database::collapse_chunks(list).into_iter().skip(offset).take(limit).collect::<Vec<FindDatabaseResult>>())
+        } else {
+            Database::find_nearest(&client, collection_name, embedding, data.limit, data.offset, &options).await
+        };
+        let nearests = match nearests {
+            Ok(mut list) if pool.is_some() => {
+                if let (Some(_), Some(query)) = (data.rerank_top_n, &rerank_query) {
+                    let model = data.rerank_model.clone().unwrap_or(settings.prompt_model.clone());
+                    list = Database::rerank(query, list, model).await;
+                }
+                if let (Some(diversity), Some(query_vector)) = (diversity, &query_vector) {
+                    list = Database::diversify(list, query_vector, diversity, page_limit + page_offset);
+                }
+                Ok(list.into_iter().skip(page_offset).take(page_limit).collect::<Vec<FindDatabaseResult>>())
+            },
+            nearests => nearests,
+        };
         match nearests {
             Ok(r) => {
                 HttpResponse::Ok().json(GeneralValueResult{result: r, status: true})
@@ -513,4 +2138,447 @@ impl Database {
             }
         }
 	} 
+
+    fn retrieved_to_result(point: RetrievedPoint) -> GetDatabaseResult {
+        let mut payload = point.payload;
+        let text = Database::take_document(&mut payload);
+        let point_id = Database::point_id_to_string(point.id);
+        let id = Database::take_original_id(&mut payload, &point_id);
+
+        GetDatabaseResult {
+            id,
+            point_id,
+            text,
+            metadata: Some(Database::take_metadata(payload)),
+            embeddings: Database::dense_vector(point.vectors),
+        }
+    }
+
+    /// Scroll cursors are the next point id; numeric ids are kept apart from UUIDs.
+    fn parse_cursor(cursor: &str) -> Option<PointId> {
+        if let Ok(num) = cursor.parse::<u64>() {
+            return Some(PointId::from(num));
+        }
+        Uuid::parse_str(cursor).ok().map(|uuid| PointId::from(uuid.to_string()))
+    }
+
+    /// Rebuilds a chunked document from its chunks. The gaps between chunks only ever held
+    /// whitespace, so they are filled with spaces to keep the chunk offsets valid.
+    fn assemble_chunks(parent_id: String, mut parts: Vec<GetDatabaseResult>) -> GetDatabaseResult {
+        let offset = |item: &GetDatabaseResult, key: &str| item.metadata.as_ref()
+            .and_then(|m| m.get(key))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as usize;
+        parts.sort_by_key(|item| offset(item, "chunk_index"));
+
+        let mut text = String::new();
+        let mut covered = 0;
+        for part in &parts {
+            let start = offset(part, "chunk_start");
+            if start > covered {
+                text.extend(std::iter::repeat_n(' ', start - covered));
+                covered = start;
+            }
+            text.extend(part.text.chars().skip(covered - start));
+            covered = covered.max(offset(part, "chunk_end"));
+        }
+
+        let mut metadata = parts.into_iter().next().and_then(|item| item.metadata).unwrap_or_default();
+        for key in CHUNK_PAYLOAD_KEYS {
+            metadata.remove(*key);
+        }
+
+        GetDatabaseResult {
+            id: parent_id,
+            point_id: "".to_string(),
+            text,
+            metadata: Some(metadata),
+            embeddings: None,
+        }
+    }
+
+    async fn get_items(client: &Qdrant, collection_name: String, ids: Vec<String>, with_vectors: bool) -> Result<Vec<GetDatabaseResult>, String> {
+        let mut user_ids: HashMap<String, usize> = HashMap::new();
+        let mut point_ids: Vec<PointId> = Vec::new();
+        for (idx, id) in ids.iter().enumerate() {
+            let point_id = Database::point_id(id);
+            point_ids.push(PointId::from(point_id.clone()));
+            user_ids.insert(point_id, idx);
+        }
+
+        let request = GetPointsBuilder::new(collection_name.clone(), point_ids)
+            .with_payload(true)
+            .with_vectors(with_vectors);
+
+        match client.get_points(request).await {
+            Ok(response) => {
+                let mut list: Vec<(usize, GetDatabaseResult)> = Vec::new();
+                for point in response.result {
+                    let item = Database::retrieved_to_result(point);
+                    let idx = user_ids.remove(&item.point_id).unwrap_or(usize::MAX);
+                    list.push((idx, item));
+                }
+
+                // Chunked documents have no point of their own, so rebuild them from their chunks
+                let missing: HashMap<String, usize> = user_ids.into_values().map(|idx| (ids[idx].clone(), idx)).collect();
+                if !missing.is_empty() {
+                    let filter = Filter::must([Condition::matches("parent_id", missing.keys().cloned().collect::<Vec<String>>())]);
+                    let points = Database::scroll_all(client, collection_name, filter, true).await?;
+                    let mut chunks: HashMap<String, Vec<GetDatabaseResult>> = HashMap::new();
+                    for point in points {
+                        let item = Database::retrieved_to_result(point);
+                        let parent_id = item.metadata.as_ref()
+                            .and_then(|m| m.get("parent_id"))
+                            .and_then(|v| v.as_str())
+                            .map(|v| v.to_string())
+                            .unwrap_or_default();
+                        chunks.entry(parent_id).or_default().push(item);
+                    }
+                    for (parent_id, parts) in chunks {
+                        if let Some(idx) = missing.get(&parent_id) {
+                            list.push((*idx, Database::assemble_chunks(parent_id, parts)));
+                        }
+                    }
+                }
+
+                // Qdrant does not keep the requested order, so restore it
+                list.sort_by_key(|(idx, _)| *idx);
+                Ok(list.into_iter().map(|(_, item)| item).collect())
+            },
+            Err(e) => {
+                println!("{}", e);
+                Err(e.to_string())
+            }
+        }
+    }
+
+	pub async fn get_item(path: web::Path<(String, String)>, query: web::Query<GetDatabaseItemQuery>) -> impl Responder {
+        let (collection_name, id) = path.into_inner();
+        let client = Database::connect();
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        let items = Database::get_items(&client, collection_name, vec![id.clone()], query.with_vectors.unwrap_or(false)).await;
+        match items {
+            Ok(list) => {
+                match list.into_iter().next() {
+                    Some(item) => HttpResponse::Ok().json(GeneralValueResult{result: item, status: true}),
+                    None => HttpResponse::NotFound().json(ErrorResult {status: false, message: Some(format!("Item '{}' does not exist", id))}),
+                }
+            },
+            Err(e) => {
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)})
+            }
+        }
+	}
+
+	pub async fn get_items_batch(path: web::Path<String>, data: web::Json<GetDatabaseItemsReq>) -> impl Responder {
+        let collection_name = path.into_inner();
+        let client = Database::connect();
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        let items = Database::get_items(&client, collection_name, data.ids.clone(), data.with_vectors.unwrap_or(false)).await;
+        match items {
+            Ok(list) => {
+                HttpResponse::Ok().json(GeneralValueResult{result: list, status: true})
+            },
+            Err(e) => {
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)})
+            }
+        }
+	}
+
+    async fn scroll_all(client: &Qdrant, collection_name: String, filter: Filter, with_payload: bool) -> Result<Vec<RetrievedPoint>, String> {
+        let mut points: Vec<RetrievedPoint> = Vec::new();
+        let mut offset: Option<PointId> = None;
+        loop {
+            let mut request = ScrollPointsBuilder::new(collection_name.clone())
+                .filter(filter.clone())
+                .limit(256)
+                .with_payload(with_payload)
+                .with_vectors(false);
+            if let Some(offset) = offset {
+                request = request.offset(offset);
+            }
+
+            let response = client.scroll(request).await.map_err(|e| e.to_string())?;
+            points.extend(response.result);
+
+            offset = response.next_page_offset;
+            if offset.is_none() {
+                break;
+            }
+        }
+
+        Ok(points)
+    }
+
+    async fn matching_ids(client: &Qdrant, collection_name: String, filter: Filter) -> Result<Vec<String>, String> {
+        let points = Database::scroll_all(client, collection_name, filter, true).await?;
+        let ids = points.into_iter()
+            .map(|point| {
+                let mut payload = point.payload;
+                let point_id = Database::point_id_to_string(point.id);
+                Database::take_original_id(&mut payload, &point_id)
+            })
+            .collect();
+        Ok(ids)
+    }
+
+    async fn count_items(client: &Qdrant, collection_name: String, filter: Filter, exact: bool) -> Result<u64, String> {
+        let request = CountPointsBuilder::new(collection_name)
+            .filter(filter)
+            .exact(exact);
+        match client.count(request).await {
+            Ok(response) => Ok(response.result.map(|r| r.count).unwrap_or(0)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn delete_items(client: &Qdrant, collection_name: String, filter: Filter, dry_run: bool) -> Result<DeleteDatabaseResult, String> {
+        let deleted = Database::count_items(client, collection_name.clone(), filter.clone(), true).await?;
+
+        if dry_run {
+            let ids = Database::matching_ids(client, collection_name, filter).await?;
+            return Ok(DeleteDatabaseResult {
+                deleted,
+                dry_run,
+                ids: Some(ids),
+            });
+        }
+
+        if deleted > 0 {
+            client
+                .delete_points(DeletePointsBuilder::new(collection_name).points(filter).wait(true))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(DeleteDatabaseResult {
+            deleted,
+            dry_run,
+            ids: None,
+        })
+    }
+
+	pub async fn delete_item(path: web::Path<(String, String)>) -> impl Responder {
+        let (collection_name, id) = path.into_inner();
+        let client = Database::connect();
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        let filter = match Database::build_exact_filter(None, Some(vec![id.clone()])) {
+            Ok(filter) => filter,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        match Database::delete_items(&client, collection_name, filter, false).await {
+            Ok(res) => {
+                if res.deleted == 0 {
+                    return HttpResponse::NotFound().json(ErrorResult {status: false, message: Some(format!("Item '{}' does not exist", id))});
+                }
+                HttpResponse::Ok().json(GeneralValueResult{result: res, status: true})
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)})
+            }
+        }
+	}
+
+	pub async fn delete_items_batch(path: web::Path<String>, data: web::Json<DeleteDatabaseReq>) -> impl Responder {
+        let collection_name = path.into_inner();
+        let client = Database::connect();
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        let no_ids = data.ids.as_ref().map(|ids| ids.is_empty()).unwrap_or(true);
+        let no_filters = data.filters.as_ref().map(|filters| filters.is_empty()).unwrap_or(true);
+        if no_ids && no_filters {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("Either ids or filters is required".to_string())});
+        }
+
+        let filter = match Database::build_exact_filter(data.filters.clone(), data.ids.clone()) {
+            Ok(filter) => filter,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        match Database::delete_items(&client, collection_name, filter, data.dry_run.unwrap_or(false)).await {
+            Ok(res) => {
+                HttpResponse::Ok().json(GeneralValueResult{result: res, status: true})
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)})
+            }
+        }
+	}
+
+    async fn patch_items(client: &Qdrant, collection_name: String, filter: Filter, data: &PatchDatabaseReq) -> Result<PatchDatabaseResult, String> {
+        let updated = Database::count_items(client, collection_name.clone(), filter.clone(), true).await?;
+        if updated == 0 {
+            return Ok(PatchDatabaseResult { updated });
+        }
+
+        let set = data.set.clone().unwrap_or_default();
+
+        if data.replace.unwrap_or(false) {
+            // Overwriting drops every key, so carry the reserved ones over point by point
+            let points = Database::scroll_all(client, collection_name.clone(), filter, true).await?;
+            for point in points {
+                let mut payload = set.clone();
+                for (key, value) in Database::payload_to_map(point.payload) {
+                    if RESERVED_PAYLOAD_KEYS.contains(&key.as_str()) || CHUNK_PAYLOAD_KEYS.contains(&key.as_str()) {
+                        payload.insert(key, value);
+                    }
+                }
+
+                let point_id = match point.id {
+                    Some(point_id) => point_id,
+                    None => continue,
+                };
+                let request = SetPayloadPointsBuilder::new(collection_name.clone(), Payload::from(payload))
+                    .points_selector(vec![point_id])
+                    .wait(true);
+                client.overwrite_payload(request).await.map_err(|e| e.to_string())?;
+            }
+
+            return Ok(PatchDatabaseResult { updated });
+        }
+
+        if let Some(keys) = &data.remove {
+            if !keys.is_empty() {
+                let request = DeletePayloadPointsBuilder::new(collection_name.clone(), keys.clone())
+                    .points_selector(filter.clone())
+                    .wait(true);
+                client.delete_payload(request).await.map_err(|e| e.to_string())?;
+            }
+        }
+
+        if !set.is_empty() {
+            let request = SetPayloadPointsBuilder::new(collection_name, Payload::from(set))
+                .points_selector(filter)
+                .wait(true);
+            client.set_payload(request).await.map_err(|e| e.to_string())?;
+        }
+
+        Ok(PatchDatabaseResult { updated })
+    }
+
+	pub async fn patch_items_batch(path: web::Path<String>, data: web::Json<PatchDatabaseReq>) -> impl Responder {
+        let collection_name = path.into_inner();
+        let client = Database::connect();
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        let no_ids = data.ids.as_ref().map(|ids| ids.is_empty()).unwrap_or(true);
+        let no_filters = data.filters.as_ref().map(|filters| filters.is_empty()).unwrap_or(true);
+        if no_ids && no_filters {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("Either ids or filters is required".to_string())});
+        }
+
+        let set_keys = data.set.iter().flat_map(|set| set.keys());
+        let remove_keys = data.remove.iter().flatten();
+        if let Some(key) = set_keys.chain(remove_keys).find(|key| RESERVED_PAYLOAD_KEYS.contains(&key.as_str()) || CHUNK_PAYLOAD_KEYS.contains(&key.as_str())) {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(format!("'{}' is managed by naomi-db and cannot be changed", key))});
+        }
+
+        let filter = match Database::build_exact_filter(data.filters.clone(), data.ids.clone()) {
+            Ok(filter) => filter,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        match Database::patch_items(&client, collection_name, filter, &data).await {
+            Ok(res) => {
+                HttpResponse::Ok().json(GeneralValueResult{result: res, status: true})
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)})
+            }
+        }
+	}
+
+	pub async fn scroll(data: web::Json<ScrollDatabaseReq>) -> impl Responder {
+        let collection_name: String = data.collection.clone();
+        let client = Database::connect();
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        let point_ids = data.filter_by_ids.as_ref().map(|ids| ids.iter().map(|id| Database::point_id(id)).collect());
+        let filter = match Database::build_filter(data.filters.clone(), point_ids) {
+            Ok(filter) => filter,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        let mut request = ScrollPointsBuilder::new(collection_name)
+            .filter(filter)
+            .limit(data.limit.unwrap_or(100))
+            .with_payload(true)
+            .with_vectors(data.with_vectors.unwrap_or(false));
+        if let Some(cursor) = &data.cursor {
+            match Database::parse_cursor(cursor) {
+                Some(offset) => request = request.offset(offset),
+                None => {
+                    return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(format!("Invalid cursor '{}'", cursor))});
+                }
+            }
+        }
+
+        match client.scroll(request).await {
+            Ok(response) => {
+                let res = ScrollDatabaseResult {
+                    items: response.result.into_iter().map(Database::retrieved_to_result).collect(),
+                    next_cursor: response.next_page_offset.map(|offset| Database::point_id_to_string(Some(offset))),
+                };
+                HttpResponse::Ok().json(GeneralValueResult{result: res, status: true})
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e.to_string())})
+            }
+        }
+	}
+
+	pub async fn count(data: web::Json<CountDatabaseReq>) -> impl Responder {
+        let collection_name: String = data.collection.clone();
+        let client = Database::connect();
+        if let Err(res) = Collection::check_exists(&client, &collection_name).await {
+            return res;
+        }
+
+        let point_ids = data.filter_by_ids.as_ref().map(|ids| ids.iter().map(|id| Database::point_id(id)).collect());
+        let filter = match Database::build_filter(data.filters.clone(), point_ids) {
+            Ok(filter) => filter,
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        let exact = data.exact.unwrap_or(true);
+        match Database::count_items(&client, collection_name, filter, exact).await {
+            Ok(count) => {
+                HttpResponse::Ok().json(GeneralValueResult{result: CountDatabaseResult {count, exact}, status: true})
+            },
+            Err(e) => {
+                println!("{}", e);
+                HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)})
+            }
+        }
+	}
 }
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use crate::models::general::*;
+use crate::models::database::*;
+use crate::models::embed::*;
+use crate::handlers::database::{EmbeddingSettings, DEFAULT_EMBEDDING_MODEL, DEFAULT_PROMPT_MODEL};
+use actix_web::*;
+use actix_web::web;
+
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PostEmbedReq {
+    pub texts: Vec<String>,
+    pub model: Option<String>,
+    pub translate_to: Option<String>,
+    pub prompt_model: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EmbedItemResult {
+    pub text: String,
+    pub english: Option<String>,
+    pub embeddings: Vec<f32>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PostEmbedResult {
+    pub model: String,
+    pub dimension: usize,
+    pub items: Vec<EmbedItemResult>,
+}
+
+impl Embed {
+	pub async fn embed(data: web::Json<PostEmbedReq>) -> impl Responder {
+        let data = data.into_inner();
+        if data.texts.is_empty() {
+            return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some("No texts to embed".to_string())});
+        }
+
+        let settings = EmbeddingSettings {
+            model: data.model.unwrap_or(DEFAULT_EMBEDDING_MODEL.to_string()),
+            translate_to: data.translate_to.filter(|t| !t.is_empty()),
+            prompt_model: data.prompt_model.unwrap_or(DEFAULT_PROMPT_MODEL.to_string()),
+        };
+
+        let ids: Vec<String> = (0..data.texts.len()).map(|idx| idx.to_string()).collect();
+        match Database::embed_texts(&settings, &ids, data.texts.clone()).await {
+            Ok(vectors) => {
+                let items: Vec<EmbedItemResult> = data.texts.into_iter().zip(vectors)
+                    .map(|(text, (embeddings, english))| EmbedItemResult { text, english, embeddings })
+                    .collect();
+                let result = PostEmbedResult {
+                    model: settings.model,
+                    dimension: items.first().map(|item| item.embeddings.len()).unwrap_or(0),
+                    items,
+                };
+                HttpResponse::Ok().json(GeneralValueResult{result, status: true})
+            },
+            Err(e) => HttpResponse::InternalServerError().json(ErrorResult {status: false, message: Some(e)}),
+        }
+	}
+}
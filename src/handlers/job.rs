@@ -0,0 +1,298 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::models::general::*;
+use crate::models::database::*;
+use crate::models::job::*;
+use crate::handlers::collection::CollectionMeta;
+use crate::handlers::database::{EmbeddingSettings, EmbeddingStatus, InsertOptions, PostDatabaseItem, PostDatabaseReq, PostDatabaseResult};
+use crate::utils::ids::IdStrategy;
+use actix_web::*;
+use actix_web::web;
+
+use qdrant_client::Qdrant;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use uuid::Uuid;
+
+
+pub const DEFAULT_JOB_BATCH_SIZE: usize = 16;
+
+/// Finished jobs kept in memory for polling; the oldest are dropped first.
+pub const MAX_FINISHED_JOBS: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PostInsertJobReq {
+    #[serde(flatten)]
+    pub insert: PostDatabaseReq,
+    pub batch_size: Option<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PostJobResult {
+    pub job_id: String,
+}
+
+/// `index` is the position of the item in the submitted request.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JobItemFailure {
+    pub index: usize,
+    pub id: Option<String>,
+    pub message: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JobItemResult {
+    pub index: usize,
+    pub id: String,
+    pub embedding_status: EmbeddingStatus,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub collection: String,
+    pub status: JobStatus,
+    pub total: usize,
+    pub processed: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub failures: Vec<JobItemFailure>,
+    pub results: Vec<JobItemResult>,
+    pub error: Option<String>,
+    pub cancel_requested: bool,
+    pub created_at: NaiveDateTime,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+fn jobs() -> &'static Mutex<HashMap<String, JobInfo>> {
+    static JOBS: OnceLock<Mutex<HashMap<String, JobInfo>>> = OnceLock::new();
+    JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+impl Job {
+
+    fn update<F: FnOnce(&mut JobInfo)>(job_id: &str, f: F) {
+        let mut jobs = jobs().lock().unwrap();
+        if let Some(job) = jobs.get_mut(job_id) {
+            f(job);
+        }
+    }
+
+    fn get(job_id: &str) -> Option<JobInfo> {
+        jobs().lock().unwrap().get(job_id).cloned()
+    }
+
+    fn register(job: JobInfo) {
+        let mut jobs = jobs().lock().unwrap();
+
+        let mut finished: Vec<(NaiveDateTime, String)> = jobs.values()
+            .filter(|j| j.status.is_finished())
+            .map(|j| (j.finished_at.unwrap_or(j.created_at), j.id.clone()))
+            .collect();
+        if finished.len() >= MAX_FINISHED_JOBS {
+            finished.sort();
+            for (_, id) in finished.iter().take(finished.len() + 1 - MAX_FINISHED_JOBS) {
+                jobs.remove(id);
+            }
+        }
+
+        jobs.insert(job.id.clone(), job);
+    }
+
+    fn finish(job_id: &str, status: JobStatus, error: Option<String>) {
+        Job::update(job_id, |job| {
+            job.status = status;
+            job.error = error;
+            job.finished_at = Some(Utc::now().naive_utc());
+        });
+    }
+
+    fn record(job_id: &str, indexes: &[usize], outcome: Result<Vec<JobItemResult>, JobItemFailure>) {
+        Job::update(job_id, |job| {
+            job.processed += indexes.len();
+            match outcome {
+                Ok(results) => {
+                    job.succeeded += indexes.len();
+                    job.results.extend(results);
+                },
+                Err(failure) => {
+                    job.failed += indexes.len();
+                    job.failures.push(failure);
+                }
+            }
+        });
+    }
+
+    /// Results of chunked items carry the chunk id; map them back by parent.
+    fn item_results(indexes: &[usize], ids: &[String], results: Vec<PostDatabaseResult>) -> Vec<JobItemResult> {
+        results.into_iter()
+            .map(|result| {
+                let parent_id = result.metadata.as_ref()
+                    .and_then(|m| m.get("parent_id"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(&result.id)
+                    .to_string();
+                let index = ids.iter().position(|id| *id == parent_id)
+                    .map(|pos| indexes[pos])
+                    .unwrap_or(indexes[0]);
+                JobItemResult {
+                    index,
+                    id: result.id,
+                    embedding_status: result.embedding_status,
+                }
+            })
+            .collect()
+    }
+
+    /// Runs the insert batch by batch, checking for cancellation in between. A failed
+    /// batch is retried item by item so a single bad document does not sink its batch.
+    async fn run_insert(job_id: String, client: Qdrant, settings: EmbeddingSettings, mut meta: Option<CollectionMeta>, options: InsertOptions, items: Vec<PostDatabaseItem>, batch_size: usize) {
+        let collection_name = match Job::get(&job_id) {
+            Some(job) => job.collection,
+            None => return,
+        };
+        Job::update(&job_id, |job| {
+            job.status = JobStatus::Running;
+            job.started_at = Some(Utc::now().naive_utc());
+        });
+
+        // Ids are generated up front so results can be mapped back to the submitted items
+        let batch_options = InsertOptions {
+            id_strategy: IdStrategy::Provided,
+            ..options.clone()
+        };
+        let indexed: Vec<(usize, PostDatabaseItem)> = items.into_iter().enumerate().collect();
+        for batch in indexed.chunks(batch_size) {
+            if Job::get(&job_id).map(|job| job.cancel_requested).unwrap_or(true) {
+                Job::finish(&job_id, JobStatus::Cancelled, None);
+                return;
+            }
+
+            let indexes: Vec<usize> = batch.iter().map(|(index, _)| *index).collect();
+            let batch_items: Vec<PostDatabaseItem> = batch.iter().map(|(_, item)| item.clone()).collect();
+            let ids: Vec<String> = batch_items.iter()
+                .map(|item| options.id_strategy.generate(item.id.as_ref(), &item.text, item.metadata.as_ref(), options.id_namespace_field.as_ref()))
+                .collect();
+            let fixed_items: Vec<PostDatabaseItem> = batch_items.into_iter().zip(&ids)
+                .map(|(item, id)| PostDatabaseItem { id: Some(id.clone()), ..item })
+                .collect();
+
+            match Database::insert_items(&client, &collection_name, &settings, &mut meta, &batch_options, fixed_items.clone()).await {
+                Ok(results) => Job::record(&job_id, &indexes, Ok(Job::item_results(&indexes, &ids, results))),
+                Err(_) if fixed_items.len() > 1 => {
+                    for ((index, item), id) in indexes.iter().zip(fixed_items).zip(&ids) {
+                        let outcome = match Database::insert_items(&client, &collection_name, &settings, &mut meta, &batch_options, vec![item]).await {
+                            Ok(results) => Ok(Job::item_results(&[*index], std::slice::from_ref(id), results)),
+                            Err(e) => Err(JobItemFailure { index: *index, id: Some(id.clone()), message: e }),
+                        };
+                        Job::record(&job_id, &[*index], outcome);
+                    }
+                },
+                Err(e) => Job::record(&job_id, &indexes, Err(JobItemFailure { index: indexes[0], id: ids.first().cloned(), message: e })),
+            }
+        }
+
+        let failed_all = Job::get(&job_id).map(|job| job.total > 0 && job.succeeded == 0).unwrap_or(false);
+        if failed_all {
+            Job::finish(&job_id, JobStatus::Failed, Some("Every item failed".to_string()));
+        } else {
+            Job::finish(&job_id, JobStatus::Completed, None);
+        }
+    }
+
+	pub async fn submit_insert(data: web::Json<PostInsertJobReq>) -> impl Responder {
+        let data = data.into_inner();
+        let collection_name = data.insert.collection.clone();
+        let client = Database::connect();
+        let (settings, meta) = match Database::load_settings(&client, &collection_name, data.insert.model.clone(), data.insert.translate_to.clone()).await {
+            Ok(r) => r,
+            Err(res) => return res,
+        };
+
+        let options = match Database::insert_options(&meta, data.insert.id_strategy, data.insert.id_namespace_field.clone(), data.insert.calculate_nearest, data.insert.chunking.clone()) {
+            Ok(options) => options.with_dedupe(data.insert.dedupe_threshold, data.insert.dedupe_action),
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResult {status: false, message: Some(e)});
+            }
+        };
+
+        let items = data.insert.data.items();
+        let job_id = Uuid::new_v4().to_string();
+        Job::register(JobInfo {
+            id: job_id.clone(),
+            collection: collection_name,
+            status: JobStatus::Queued,
+            total: items.len(),
+            processed: 0,
+            succeeded: 0,
+            failed: 0,
+            failures: Vec::new(),
+            results: Vec::new(),
+            error: None,
+            cancel_requested: false,
+            created_at: Utc::now().naive_utc(),
+            started_at: None,
+            finished_at: None,
+        });
+
+        let batch_size = data.batch_size.unwrap_or(DEFAULT_JOB_BATCH_SIZE).max(1);
+        rt::spawn(Job::run_insert(job_id.clone(), client, settings, meta, options, items, batch_size));
+
+        HttpResponse::Accepted().json(GeneralValueResult{result: PostJobResult {job_id}, status: true})
+	}
+
+	pub async fn list() -> impl Responder {
+        let mut list: Vec<JobInfo> = jobs().lock().unwrap().values()
+            .map(|job| JobInfo {
+                failures: Vec::new(),
+                results: Vec::new(),
+                ..job.clone()
+            })
+            .collect();
+        list.sort_by_key(|job| std::cmp::Reverse(job.created_at));
+
+        HttpResponse::Ok().json(GeneralValueResult{result: list, status: true})
+	}
+
+	pub async fn info(path: web::Path<String>) -> impl Responder {
+        match Job::get(&path.into_inner()) {
+            Some(job) => HttpResponse::Ok().json(GeneralValueResult{result: job, status: true}),
+            None => HttpResponse::NotFound().json(ErrorResult {status: false, message: Some("Job not found".to_string())}),
+        }
+	}
+
+	pub async fn cancel(path: web::Path<String>) -> impl Responder {
+        let job_id = path.into_inner();
+        let job = match Job::get(&job_id) {
+            Some(job) => job,
+            None => {
+                return HttpResponse::NotFound().json(ErrorResult {status: false, message: Some("Job not found".to_string())});
+            }
+        };
+        if job.status.is_finished() {
+            return HttpResponse::Conflict().json(ErrorResult {status: false, message: Some("Job has already finished".to_string())});
+        }
+
+        Job::update(&job_id, |job| {
+            job.cancel_requested = true;
+        });
+        HttpResponse::Accepted().json(GeneralValueResult{result: Job::get(&job_id), status: true})
+	}
+}
@@ -1,2 +1,6 @@
 pub mod health;
 pub mod database;
+pub mod collection;
+pub mod job;
+pub mod cache;
+pub mod embed;
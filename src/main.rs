@@ -2,6 +2,10 @@ use actix_cors::Cors;
 use std::env;
 use crate::models::health;
 use crate::models::database;
+use crate::models::collection;
+use crate::models::job;
+use crate::models::cache;
+use crate::models::embed;
 use dotenvy::dotenv;
 use actix_web::{web, App, HttpServer};
 
@@ -46,9 +50,35 @@ async fn main() -> std::io::Result<()> {
             
             .route("/api/v1/health", web::get().to(health::Health::check))
 
+            .route("/api/v1/embed", web::post().to(embed::Embed::embed))
+
             .route("/api/v1/database", web::post().to(database::Database::insert))
+            .route("/api/v1/database/upload", web::post().to(database::Database::upload))
             .route("/api/v1/database/embeddings", web::post().to(database::Database::insert_embeddings))
+            .route("/api/v1/database/{collection}/import", web::post().to(database::Database::import))
+            .route("/api/v1/database/{collection}/import/csv", web::post().to(database::Database::import_csv))
             .route("/api/v1/database/find", web::post().to(database::Database::find))
+            .route("/api/v1/database/scroll", web::post().to(database::Database::scroll))
+            .route("/api/v1/database/count", web::post().to(database::Database::count))
+            .route("/api/v1/database/{collection}/items/get", web::post().to(database::Database::get_items_batch))
+            .route("/api/v1/database/{collection}/items", web::patch().to(database::Database::patch_items_batch))
+            .route("/api/v1/database/{collection}/items/delete", web::post().to(database::Database::delete_items_batch))
+            .route("/api/v1/database/{collection}/items/{id}", web::get().to(database::Database::get_item))
+            .route("/api/v1/database/{collection}/items/{id}", web::delete().to(database::Database::delete_item))
+
+            .route("/api/v1/collections", web::get().to(collection::Collection::list))
+            .route("/api/v1/collections", web::post().to(collection::Collection::create))
+            .route("/api/v1/collections/{name}", web::get().to(collection::Collection::info))
+            .route("/api/v1/collections/{name}", web::delete().to(collection::Collection::delete))
+            .route("/api/v1/collections/{name}/text-index", web::put().to(collection::Collection::put_text_index))
+
+            .route("/api/v1/jobs", web::get().to(job::Job::list))
+            .route("/api/v1/jobs/insert", web::post().to(job::Job::submit_insert))
+            .route("/api/v1/jobs/{id}", web::get().to(job::Job::info))
+            .route("/api/v1/jobs/{id}/cancel", web::post().to(job::Job::cancel))
+
+            .route("/api/v1/cache", web::get().to(cache::Cache::stats))
+            .route("/api/v1/cache", web::delete().to(cache::Cache::clear))
     })
     .workers(2)
     .bind(listen_url)?
@@ -0,0 +1,8 @@
+#![allow(non_snake_case)]
+
+use serde::{Deserialize, Serialize};
+use std::*;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Cache {
+}
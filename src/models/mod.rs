@@ -1,3 +1,7 @@
 pub mod health;
 pub mod general;
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod collection;
+pub mod job;
+pub mod cache;
+pub mod embed;
\ No newline at end of file
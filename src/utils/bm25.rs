@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+
+/// Name of the sparse vector holding the BM25 term weights of `original_document`.
+pub const SPARSE_VECTOR_NAME: &str = "bm25";
+
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+/// Qdrant applies the IDF part at query time, but it has no corpus statistics
+/// for length normalization, so a typical document length is assumed instead.
+const AVG_DOC_LEN: f32 = 256.0;
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it",
+    "of", "on", "or", "that", "the", "this", "to", "was", "were", "with",
+];
+
+/// Lowercases the text and splits it on anything that is not a letter or a digit.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty() && !STOPWORDS.contains(token))
+        .map(|token| token.to_string())
+        .collect()
+}
+
+fn token_index(token: &str) -> u32 {
+    let digest = md5::compute(token.as_bytes());
+    u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]])
+}
+
+fn to_sparse(weights: HashMap<u32, f32>) -> (Vec<u32>, Vec<f32>) {
+    let mut pairs: Vec<(u32, f32)> = weights.into_iter().collect();
+    pairs.sort_by_key(|(index, _)| *index);
+    pairs.into_iter().unzip()
+}
+
+/// BM25 term-frequency weights of a document as sparse (indices, values).
+pub fn document_vector(text: &str) -> (Vec<u32>, Vec<f32>) {
+    let tokens = tokenize(text);
+    let doc_len = tokens.len() as f32;
+
+    let mut frequencies: HashMap<u32, f32> = HashMap::new();
+    for token in &tokens {
+        *frequencies.entry(token_index(token)).or_insert(0.0) += 1.0;
+    }
+
+    let norm = K1 * (1.0 - B + B * doc_len / AVG_DOC_LEN);
+    let weights = frequencies.into_iter()
+        .map(|(index, tf)| (index, tf * (K1 + 1.0) / (tf + norm)))
+        .collect();
+    to_sparse(weights)
+}
+
+/// Every distinct query term weighs the same; IDF is applied by Qdrant.
+pub fn query_vector(text: &str) -> (Vec<u32>, Vec<f32>) {
+    let weights = tokenize(text).iter()
+        .map(|token| (token_index(token), 1.0))
+        .collect();
+    to_sparse(weights)
+}
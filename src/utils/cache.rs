@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+
+pub const DEFAULT_CACHE_PATH: &str = "embedding_cache";
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 100_000;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EmbeddingCacheStats {
+    pub enabled: bool,
+    pub entries: usize,
+    pub max_entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub hit_rate: f64,
+    pub size_on_disk: u64,
+}
+
+/// On-disk cache of embeddings keyed by (model, normalized text). Entries are
+/// evicted oldest-first once `max_entries` is exceeded.
+pub struct EmbeddingCache {
+    db: sled::Db,
+    vectors: sled::Tree,
+    order: sled::Tree,
+    entries: AtomicUsize,
+    max_entries: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Whitespace differences do not change what gets embedded in any useful way.
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+fn encode(id: u64, embeddings: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(8 + embeddings.len() * 4);
+    bytes.extend_from_slice(&id.to_be_bytes());
+    for v in embeddings {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    bytes
+}
+
+fn decode(bytes: &[u8]) -> Option<(u64, Vec<f32>)> {
+    if bytes.len() < 8 || !(bytes.len() - 8).is_multiple_of(4) {
+        return None;
+    }
+    let id = u64::from_be_bytes(bytes[..8].try_into().ok()?);
+    let embeddings = bytes[8..]
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    Some((id, embeddings))
+}
+
+impl EmbeddingCache {
+    pub fn get_path() -> String {
+        use std::env;
+        env::var("EMBEDDING_CACHE_PATH").unwrap_or(DEFAULT_CACHE_PATH.to_string())
+    }
+
+    /// `EMBEDDING_CACHE_MAX_ENTRIES=0` turns the cache off.
+    pub fn get_max_entries() -> usize {
+        use std::env;
+        env::var("EMBEDDING_CACHE_MAX_ENTRIES").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_CACHE_MAX_ENTRIES)
+    }
+
+    /// The process-wide cache, or `None` when it is disabled or could not be opened.
+    pub fn global() -> Option<&'static EmbeddingCache> {
+        static CACHE: OnceLock<Option<EmbeddingCache>> = OnceLock::new();
+        CACHE.get_or_init(EmbeddingCache::open).as_ref()
+    }
+
+    fn open() -> Option<EmbeddingCache> {
+        let max_entries = EmbeddingCache::get_max_entries();
+        if max_entries == 0 {
+            return None;
+        }
+
+        let path = EmbeddingCache::get_path();
+        let opened = sled::open(&path).and_then(|db| {
+            let vectors = db.open_tree("vectors")?;
+            let order = db.open_tree("order")?;
+            Ok((db, vectors, order))
+        });
+        match opened {
+            Ok((db, vectors, order)) => {
+                let entries = vectors.len();
+                Some(EmbeddingCache {
+                    db,
+                    vectors,
+                    order,
+                    entries: AtomicUsize::new(entries),
+                    max_entries,
+                    hits: AtomicU64::new(0),
+                    misses: AtomicU64::new(0),
+                })
+            },
+            Err(e) => {
+                println!("Embedding cache at '{}' is disabled: {}", path, e);
+                None
+            }
+        }
+    }
+
+    fn key(model: &str, text: &str) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(normalize(text).as_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    pub fn get(&self, model: &str, text: &str) -> Option<Vec<f32>> {
+        let found = self.vectors.get(EmbeddingCache::key(model, text))
+            .ok()
+            .flatten()
+            .and_then(|bytes| decode(&bytes))
+            .map(|(_, embeddings)| embeddings);
+        match found {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => self.misses.fetch_add(1, Ordering::Relaxed),
+        };
+        found
+    }
+
+    pub fn put(&self, model: &str, text: &str, embeddings: &[f32]) {
+        let key = EmbeddingCache::key(model, text);
+        let id = match self.db.generate_id() {
+            Ok(id) => id,
+            Err(_) => return,
+        };
+
+        match self.vectors.insert(&key, encode(id, embeddings)) {
+            Ok(None) => {
+                self.entries.fetch_add(1, Ordering::Relaxed);
+            },
+            Ok(Some(_)) => {},
+            Err(e) => {
+                println!("Embedding cache write failed: {}", e);
+                return;
+            }
+        }
+        let _ = self.order.insert(id.to_be_bytes(), key);
+
+        self.evict();
+    }
+
+    /// Drops the oldest entries until the cache fits `max_entries`. Order records of
+    /// keys that were written again later are stale and only removed from the log.
+    fn evict(&self) {
+        while self.entries.load(Ordering::Relaxed) > self.max_entries {
+            let (id, key) = match self.order.pop_min() {
+                Ok(Some(entry)) => entry,
+                _ => return,
+            };
+            let current = self.vectors.get(&key).ok().flatten().and_then(|bytes| decode(&bytes));
+            if let Some((current_id, _)) = current {
+                if current_id.to_be_bytes().as_slice() == id.as_ref() && matches!(self.vectors.remove(&key), Ok(Some(_))) {
+                    self.entries.fetch_sub(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        self.vectors.clear().map_err(|e| e.to_string())?;
+        self.order.clear().map_err(|e| e.to_string())?;
+        self.entries.store(0, Ordering::Relaxed);
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn stats(&self) -> EmbeddingCacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        EmbeddingCacheStats {
+            enabled: true,
+            entries: self.entries.load(Ordering::Relaxed),
+            max_entries: self.max_entries,
+            hits,
+            misses,
+            hit_rate: if lookups > 0 { hits as f64 / lookups as f64 } else { 0.0 },
+            size_on_disk: self.db.size_on_disk().unwrap_or(0),
+        }
+    }
+}
+
+impl EmbeddingCacheStats {
+    pub fn disabled() -> EmbeddingCacheStats {
+        EmbeddingCacheStats {
+            enabled: false,
+            entries: 0,
+            max_entries: 0,
+            hits: 0,
+            misses: 0,
+            hit_rate: 0.0,
+            size_on_disk: 0,
+        }
+    }
+}
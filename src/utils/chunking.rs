@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+
+
+const DEFAULT_CHUNK_SIZE: usize = 1000;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkMode {
+    Characters,
+    Sentences,
+    Paragraphs,
+    Markdown,
+}
+
+/// `size` and `overlap` are counted in characters for every mode; the other
+/// modes only decide where a chunk is allowed to end.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChunkingOptions {
+    pub mode: ChunkMode,
+    pub size: Option<usize>,
+    pub overlap: Option<usize>,
+}
+
+/// A piece of a document; `start` and `end` are character offsets into it.
+#[derive(Clone)]
+pub struct Chunk {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Narrows a byte range so it does not start or end with whitespace.
+fn trimmed(text: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+    let slice = &text[start..end];
+    let leading = slice.len() - slice.trim_start().len();
+    let trailing = slice.len() - slice.trim_end().len();
+    if leading == slice.len() {
+        return None;
+    }
+    Some((start + leading, end - trailing))
+}
+
+fn sentence_units(text: &str) -> Vec<(usize, usize)> {
+    let mut units = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let end = idx + c.len_utf8();
+        let boundary = match c {
+            '\n' => true,
+            // Full-width stops are not followed by a space in CJK text
+            '。' | '！' | '？' => true,
+            '.' | '!' | '?' | '؟' => chars.peek().map(|(_, next)| next.is_whitespace()).unwrap_or(true),
+            _ => false,
+        };
+        if boundary {
+            if let Some(unit) = trimmed(text, start, end) {
+                units.push(unit);
+            }
+            start = end;
+        }
+    }
+    if let Some(unit) = trimmed(text, start, text.len()) {
+        units.push(unit);
+    }
+    units
+}
+
+fn paragraph_units(text: &str) -> Vec<(usize, usize)> {
+    let mut units = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim().is_empty() {
+            if let Some(unit) = trimmed(text, start, offset) {
+                units.push(unit);
+            }
+            start = offset + line.len();
+        }
+        offset += line.len();
+    }
+    if let Some(unit) = trimmed(text, start, text.len()) {
+        units.push(unit);
+    }
+    units
+}
+
+fn markdown_units(text: &str) -> Vec<(usize, usize)> {
+    let mut units = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut in_code = false;
+    for line in text.split_inclusive('\n') {
+        let trimmed_line = line.trim_start();
+        if trimmed_line.starts_with("```") {
+            in_code = !in_code;
+        }
+        if !in_code && trimmed_line.starts_with('#') && offset > start {
+            if let Some(unit) = trimmed(text, start, offset) {
+                units.push(unit);
+            }
+            start = offset;
+        }
+        offset += line.len();
+    }
+    if let Some(unit) = trimmed(text, start, text.len()) {
+        units.push(unit);
+    }
+    units
+}
+
+/// Cuts a byte range into windows of at most `size` characters.
+fn character_units(text: &str, start: usize, end: usize, size: usize, overlap: usize) -> Vec<(usize, usize)> {
+    let offsets: Vec<usize> = text[start..end]
+        .char_indices()
+        .map(|(idx, _)| start + idx)
+        .chain(std::iter::once(end))
+        .collect();
+    let count = offsets.len() - 1;
+    let step = size.saturating_sub(overlap).max(1);
+
+    let mut units = Vec::new();
+    let mut first = 0;
+    while first < count {
+        let last = (first + size).min(count);
+        units.push((offsets[first], offsets[last]));
+        if last == count {
+            break;
+        }
+        first += step;
+    }
+    units
+}
+
+pub fn split(text: &str, options: &ChunkingOptions) -> Vec<Chunk> {
+    let size = options.size.unwrap_or(DEFAULT_CHUNK_SIZE).max(1);
+    let overlap = options.overlap.unwrap_or(0).min(size - 1);
+
+    let units = match options.mode {
+        ChunkMode::Characters => character_units(text, 0, text.len(), size, overlap),
+        ChunkMode::Sentences => sentence_units(text),
+        ChunkMode::Paragraphs => paragraph_units(text),
+        ChunkMode::Markdown => markdown_units(text),
+    };
+
+    // Units longer than a chunk are cut by characters so no chunk exceeds `size`
+    let mut pieces: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in units {
+        if char_len(&text[start..end]) > size {
+            pieces.extend(character_units(text, start, end, size, overlap));
+        } else {
+            pieces.push((start, end));
+        }
+    }
+
+    // Pack consecutive pieces into chunks, repeating trailing pieces up to `overlap` characters
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut current: Vec<(usize, usize)> = Vec::new();
+    for piece in pieces {
+        if let Some(first) = current.first() {
+            if char_len(&text[first.0..piece.1]) > size {
+                let last_end = current.last().unwrap().1;
+                ranges.push((first.0, last_end));
+
+                let mut carried: Vec<(usize, usize)> = Vec::new();
+                for unit in current.iter().rev() {
+                    if char_len(&text[unit.0..last_end]) > overlap || char_len(&text[unit.0..piece.1]) > size {
+                        break;
+                    }
+                    carried.insert(0, *unit);
+                }
+                current = carried;
+            }
+        }
+        current.push(piece);
+    }
+    if let (Some(first), Some(last)) = (current.first(), current.last()) {
+        ranges.push((first.0, last.1));
+    }
+
+    ranges.into_iter()
+        .map(|(start, end)| Chunk {
+            text: text[start..end].to_string(),
+            start: char_len(&text[..start]),
+            end: char_len(&text[..end]),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(mode: ChunkMode, size: usize, overlap: usize) -> ChunkingOptions {
+        ChunkingOptions { mode, size: Some(size), overlap: Some(overlap) }
+    }
+
+    fn texts(chunks: &[Chunk]) -> Vec<&str> {
+        chunks.iter().map(|chunk| chunk.text.as_str()).collect()
+    }
+
+    #[test]
+    fn split_carries_overlapping_sentences() {
+        let chunks = split("One. Two. Three.", &options(ChunkMode::Sentences, 11, 4));
+        assert_eq!(texts(&chunks), vec!["One. Two.", "Two. Three."]);
+        assert_eq!((chunks[1].start, chunks[1].end), (5, 16));
+    }
+
+    #[test]
+    fn split_cuts_units_longer_than_a_chunk() {
+        let chunks = split("abcdefghij. Short.", &options(ChunkMode::Sentences, 4, 0));
+        assert_eq!(texts(&chunks), vec!["abcd", "efgh", "ij.", "Shor", "t."]);
+
+        let chunks = split("abcdefghij", &options(ChunkMode::Characters, 4, 1));
+        assert_eq!(texts(&chunks), vec!["abcd", "defg", "ghij"]);
+    }
+
+    #[test]
+    fn split_reports_character_offsets() {
+        let chunks = split("日本語のテキスト。次の文。", &options(ChunkMode::Sentences, 9, 0));
+        assert_eq!(texts(&chunks), vec!["日本語のテキスト。", "次の文。"]);
+        assert_eq!((chunks[0].start, chunks[0].end), (0, 9));
+        assert_eq!((chunks[1].start, chunks[1].end), (9, 13));
+    }
+}
@@ -0,0 +1,240 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvColumnType {
+    String,
+    Int,
+    Float,
+    Bool,
+    Datetime,
+    StringList,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CsvMetadataColumn {
+    pub column: String,
+    /// Metadata key; defaults to the column name
+    pub field: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: CsvColumnType,
+    /// Separator of `string_list` values, `,` by default
+    pub separator: Option<String>,
+    /// chrono format of `datetime` values; RFC 3339 and ISO dates are always accepted
+    pub format: Option<String>,
+}
+
+/// How CSV rows become documents. The text is either `text_template`, with
+/// `{column}` placeholders, or the non-empty `text_columns` joined by `text_separator`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CsvMapping {
+    pub id_column: Option<String>,
+    pub text_columns: Option<Vec<String>>,
+    pub text_separator: Option<String>,
+    pub text_template: Option<String>,
+    pub metadata: Option<Vec<CsvMetadataColumn>>,
+    pub delimiter: Option<char>,
+}
+
+/// A CSV row mapped to an insertable document.
+pub struct CsvRow {
+    pub id: Option<String>,
+    pub text: String,
+    pub metadata: Map<String, Value>,
+}
+
+impl CsvMapping {
+    /// The csv reader splits on a single byte, so only ASCII delimiters are accepted.
+    pub fn delimiter_byte(&self) -> Result<u8, String> {
+        match self.delimiter {
+            Some(d) if d.is_ascii() => Ok(d as u8),
+            Some(d) => Err(format!("Delimiter '{}' is not an ASCII character", d)),
+            None => Ok(b','),
+        }
+    }
+
+    /// Checks the delimiter and that every mapped column exists in the header.
+    pub fn validate(&self, headers: &[String]) -> Result<(), String> {
+        self.delimiter_byte()?;
+        if self.text_template.is_none() && self.text_columns.as_ref().map(|c| c.is_empty()).unwrap_or(true) {
+            return Err("The mapping needs text_columns or text_template".to_string());
+        }
+
+        let mut columns: Vec<&String> = Vec::new();
+        columns.extend(self.id_column.iter());
+        columns.extend(self.text_columns.iter().flatten());
+        columns.extend(self.metadata.iter().flatten().map(|m| &m.column));
+        if let Some(template) = &self.text_template {
+            for placeholder in template_placeholders(template) {
+                if !headers.contains(&placeholder) {
+                    return Err(format!("Unknown column '{}' in text_template", placeholder));
+                }
+            }
+        }
+        match columns.into_iter().find(|c| !headers.contains(c)) {
+            Some(column) => Err(format!("Unknown column '{}'", column)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn map_row(&self, row: &HashMap<&str, &str>) -> Result<CsvRow, String> {
+        let id = self.id_column.as_ref()
+            .and_then(|column| row.get(column.as_str()))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+
+        let text = match &self.text_template {
+            Some(template) => render_template(template, row),
+            None => {
+                let separator = self.text_separator.clone().unwrap_or("\n".to_string());
+                self.text_columns.iter().flatten()
+                    .filter_map(|column| row.get(column.as_str()))
+                    .map(|v| v.trim())
+                    .filter(|v| !v.is_empty())
+                    .collect::<Vec<&str>>()
+                    .join(&separator)
+            }
+        };
+        if text.trim().is_empty() {
+            return Err("Row has no text".to_string());
+        }
+
+        let mut metadata = Map::new();
+        for column in self.metadata.iter().flatten() {
+            let raw = row.get(column.column.as_str()).map(|v| v.trim()).unwrap_or("");
+            if raw.is_empty() {
+                continue;
+            }
+            let value = convert(column, raw).map_err(|e| format!("Column '{}': {}", column.column, e))?;
+            metadata.insert(column.field.clone().unwrap_or(column.column.clone()), value);
+        }
+
+        Ok(CsvRow { id, text, metadata })
+    }
+}
+
+fn template_placeholders(template: &str) -> Vec<String> {
+    let mut placeholders = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        match rest[open..].find('}') {
+            Some(close) => {
+                placeholders.push(rest[open + 1..open + close].to_string());
+                rest = &rest[open + close + 1..];
+            },
+            None => break,
+        }
+    }
+    placeholders
+}
+
+fn render_template(template: &str, row: &HashMap<&str, &str>) -> String {
+    let mut text = template.to_string();
+    for placeholder in template_placeholders(template) {
+        let value = row.get(placeholder.as_str()).map(|v| v.trim()).unwrap_or("");
+        text = text.replace(&format!("{{{}}}", placeholder), value);
+    }
+    text.trim().to_string()
+}
+
+fn parse_bool(raw: &str) -> Result<bool, String> {
+    match raw.to_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" => Ok(true),
+        "false" | "no" | "n" | "0" => Ok(false),
+        _ => Err(format!("'{}' is not a bool", raw)),
+    }
+}
+
+/// Datetimes are stored as RFC 3339 strings, the format Qdrant's datetime index reads.
+fn parse_datetime(raw: &str, format: Option<&String>) -> Result<String, String> {
+    if let Some(format) = format {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(raw, format) {
+            return Ok(datetime.and_utc().to_rfc3339());
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(raw, format) {
+            return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().to_rfc3339());
+        }
+        return Err(format!("'{}' does not match '{}'", raw, format));
+    }
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(datetime.to_rfc3339());
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(raw, format) {
+            return Ok(datetime.and_utc().to_rfc3339());
+        }
+    }
+    match NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().to_rfc3339()),
+        Err(_) => Err(format!("'{}' is not a datetime", raw)),
+    }
+}
+
+fn convert(column: &CsvMetadataColumn, raw: &str) -> Result<Value, String> {
+    match column.kind {
+        CsvColumnType::String => Ok(json!(raw)),
+        CsvColumnType::Int => raw.parse::<i64>().map(|v| json!(v)).map_err(|_| format!("'{}' is not an int", raw)),
+        CsvColumnType::Float => raw.parse::<f64>().map(|v| json!(v)).map_err(|_| format!("'{}' is not a float", raw)),
+        CsvColumnType::Bool => parse_bool(raw).map(|v| json!(v)),
+        CsvColumnType::Datetime => parse_datetime(raw, column.format.as_ref()).map(|v| json!(v)),
+        CsvColumnType::StringList => {
+            let separator = column.separator.clone().unwrap_or(",".to_string());
+            let list: Vec<&str> = raw.split(separator.as_str())
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .collect();
+            Ok(json!(list))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, kind: CsvColumnType) -> CsvMetadataColumn {
+        CsvMetadataColumn { column: name.to_string(), field: None, kind, separator: None, format: None }
+    }
+
+    #[test]
+    fn convert_parses_typed_columns() {
+        assert_eq!(convert(&column("n", CsvColumnType::Int), "42"), Ok(json!(42)));
+        assert_eq!(convert(&column("n", CsvColumnType::Float), "2.5"), Ok(json!(2.5)));
+        assert_eq!(convert(&column("n", CsvColumnType::Bool), "Yes"), Ok(json!(true)));
+        assert_eq!(convert(&column("n", CsvColumnType::StringList), "a, b,,c"), Ok(json!(["a", "b", "c"])));
+        assert_eq!(convert(&column("n", CsvColumnType::Datetime), "2024-03-01"), Ok(json!("2024-03-01T00:00:00+00:00")));
+
+        let dated = CsvMetadataColumn { format: Some("%d/%m/%Y".to_string()), ..column("n", CsvColumnType::Datetime) };
+        assert_eq!(convert(&dated, "01/03/2024"), Ok(json!("2024-03-01T00:00:00+00:00")));
+        assert!(convert(&column("n", CsvColumnType::Int), "4.2").is_err());
+    }
+
+    #[test]
+    fn map_row_builds_text_and_metadata() {
+        let mapping = CsvMapping {
+            id_column: Some("sku".to_string()),
+            text_columns: None,
+            text_separator: None,
+            text_template: Some("{name}: {description}".to_string()),
+            metadata: Some(vec![
+                CsvMetadataColumn { field: Some("stock".to_string()), ..column("qty", CsvColumnType::Int) },
+                column("price", CsvColumnType::Float),
+            ]),
+            delimiter: None,
+        };
+        let row = HashMap::from([("sku", " A1 "), ("name", "Lamp"), ("description", "Bright"), ("qty", "3"), ("price", "")]);
+
+        let mapped = mapping.map_row(&row).unwrap();
+        assert_eq!(mapped.id.as_deref(), Some("A1"));
+        assert_eq!(mapped.text, "Lamp: Bright");
+        assert_eq!(Value::Object(mapped.metadata), json!({"stock": 3}));
+
+        let row = HashMap::from([("sku", "A2"), ("name", "Lamp"), ("description", "Bright"), ("qty", "many")]);
+        assert_eq!(mapping.map_row(&row).err().as_deref(), Some("Column 'qty': 'many' is not an int"));
+    }
+}
@@ -0,0 +1,277 @@
+use serde_json::Value;
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FileKind {
+    Text,
+    Markdown,
+    Html,
+    Json,
+    Csv,
+}
+
+impl FileKind {
+    /// Picks the kind from the file extension, falling back to the mime type.
+    pub fn detect(filename: &str, mime: Option<&str>) -> Option<FileKind> {
+        let extension = filename.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+        match extension.as_deref() {
+            Some("txt") | Some("text") => return Some(FileKind::Text),
+            Some("md") | Some("markdown") => return Some(FileKind::Markdown),
+            Some("html") | Some("htm") => return Some(FileKind::Html),
+            Some("json") => return Some(FileKind::Json),
+            Some("csv") => return Some(FileKind::Csv),
+            _ => {}
+        }
+
+        match mime.map(|m| m.split(';').next().unwrap_or("").trim().to_lowercase()).as_deref() {
+            Some("text/plain") => Some(FileKind::Text),
+            Some("text/markdown") => Some(FileKind::Markdown),
+            Some("text/html") => Some(FileKind::Html),
+            Some("application/json") => Some(FileKind::Json),
+            Some("text/csv") => Some(FileKind::Csv),
+            _ => None,
+        }
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Collapses runs of blank lines and trailing spaces left behind by stripped markup.
+fn tidy(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut blank = false;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            if !blank && !lines.is_empty() {
+                lines.push("");
+            }
+            blank = true;
+        } else {
+            lines.push(line);
+            blank = false;
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+fn strip_html(html: &str) -> String {
+    const BLOCK_TAGS: &[&str] = &["p", "div", "br", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6", "section", "article", "blockquote", "pre", "table", "ul", "ol"];
+
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        text.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        if rest.starts_with("<!--") {
+            rest = rest.find("-->").map(|end| &rest[end + 3..]).unwrap_or("");
+            continue;
+        }
+
+        let close = match rest.find('>') {
+            Some(close) => close,
+            None => break,
+        };
+        let tag = rest[1..close].trim_start_matches('/').trim();
+        let name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("").to_lowercase();
+        rest = &rest[close + 1..];
+
+        // Drop everything inside script and style elements
+        if (name == "script" || name == "style") && !tag.ends_with('/') {
+            let end_tag = format!("</{}", name);
+            // ASCII lowercasing keeps byte offsets valid for slicing `rest`
+            rest = match rest.to_ascii_lowercase().find(&end_tag) {
+                Some(end) => rest[end..].find('>').map(|c| &rest[end + c + 1..]).unwrap_or(""),
+                None => "",
+            };
+            continue;
+        }
+
+        if BLOCK_TAGS.contains(&name.as_str()) {
+            text.push('\n');
+        }
+    }
+    text.push_str(rest);
+
+    tidy(&decode_entities(&text))
+}
+
+fn strip_markdown(markdown: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            continue;
+        }
+        if !trimmed.is_empty() && trimmed.chars().all(|c| c == '-' || c == '*' || c == '_' || c == '=' || c == ' ') {
+            continue;
+        }
+
+        let mut line = trimmed.trim_start_matches('#').trim_start_matches('>').trim_start().to_string();
+        for marker in ["- ", "* ", "+ "] {
+            if let Some(stripped) = line.strip_prefix(marker) {
+                line = stripped.to_string();
+            }
+        }
+        lines.push(strip_inline_markdown(&line));
+    }
+
+    tidy(&lines.join("\n"))
+}
+
+/// Replaces links and images with their text and drops paired emphasis markers.
+fn strip_inline_markdown(line: &str) -> String {
+    let mut out: Vec<char> = Vec::new();
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let is_image = c == '!' && chars.get(i + 1) == Some(&'[');
+        if c == '[' || is_image {
+            let open = if is_image { i + 1 } else { i };
+            let close = chars[open..].iter().position(|&c| c == ']').map(|p| open + p);
+            if let Some(close) = close {
+                if chars.get(close + 1) == Some(&'(') {
+                    if let Some(end) = chars[close..].iter().position(|&c| c == ')').map(|p| close + p) {
+                        out.extend(&chars[open + 1..close]);
+                        i = end + 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        out.push(c);
+        i += 1;
+    }
+    strip_emphasis(&out)
+}
+
+/// Drops runs of `*`, `_`, `` ` `` or `~` that open and close a span at word boundaries,
+/// so `snake_case_name` and `2*3` are left alone.
+fn strip_emphasis(chars: &[char]) -> String {
+    let mut removed = vec![false; chars.len()];
+    // Open runs as (start, length, marker)
+    let mut open: Vec<(usize, usize, char)> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if !matches!(c, '*' | '_' | '`' | '~') {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && chars[i] == c {
+            i += 1;
+        }
+        let len = i - start;
+        let prev = start.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i).copied();
+        let can_open = next.map(|n| !n.is_whitespace()).unwrap_or(false) && prev.map(|p| !p.is_alphanumeric()).unwrap_or(true);
+        let can_close = prev.map(|p| !p.is_whitespace()).unwrap_or(false) && next.map(|n| !n.is_alphanumeric()).unwrap_or(true);
+
+        if can_close {
+            if let Some(pos) = open.iter().rposition(|&(_, l, m)| m == c && l == len) {
+                let (open_start, open_len, _) = open[pos];
+                removed[open_start..open_start + open_len].fill(true);
+                removed[start..i].fill(true);
+                open.truncate(pos);
+                continue;
+            }
+        }
+        if can_open {
+            open.push((start, len, c));
+        }
+    }
+
+    chars.iter()
+        .zip(removed)
+        .filter(|(_, removed)| !removed)
+        .map(|(c, _)| *c)
+        .collect()
+}
+
+fn json_strings(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => out.push(s.clone()),
+        Value::Array(list) => {
+            for v in list {
+                json_strings(v, out);
+            }
+        },
+        Value::Object(map) => {
+            for v in map.values() {
+                json_strings(v, out);
+            }
+        },
+        _ => {}
+    }
+}
+
+fn csv_text(content: &str) -> Result<String, String> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(content.as_bytes());
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+
+    let mut rows: Vec<String> = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let fields: Vec<String> = record.iter()
+            .enumerate()
+            .filter(|(_, value)| !value.trim().is_empty())
+            .map(|(idx, value)| match headers.get(idx) {
+                Some(header) => format!("{}: {}", header, value),
+                None => value.to_string(),
+            })
+            .collect();
+        rows.push(fields.join(", "));
+    }
+
+    // Blank lines between rows let paragraph chunking keep rows whole
+    Ok(rows.join("\n\n"))
+}
+
+/// Extracts plain text from an uploaded file of the given kind.
+pub fn extract_text(kind: FileKind, bytes: &[u8]) -> Result<String, String> {
+    let content = String::from_utf8_lossy(bytes);
+    match kind {
+        FileKind::Text => Ok(tidy(&content)),
+        FileKind::Markdown => Ok(strip_markdown(&content)),
+        FileKind::Html => Ok(strip_html(&content)),
+        FileKind::Json => {
+            let value: Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+            let mut strings: Vec<String> = Vec::new();
+            json_strings(&value, &mut strings);
+            Ok(strings.join("\n\n"))
+        },
+        FileKind::Csv => csv_text(&content),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_html_skips_non_ascii_script_and_style() {
+        let html = format!("<script>{}</script>日本<style>{}</STYLE>語", "İ".repeat(10), "K".repeat(10));
+        assert_eq!(strip_html(&html), "日本語");
+    }
+
+    #[test]
+    fn strip_inline_markdown_keeps_unpaired_markers() {
+        assert_eq!(strip_inline_markdown("call snake_case_name with SKU_123"), "call snake_case_name with SKU_123");
+        assert_eq!(strip_inline_markdown("2*3 = 6 and a * b"), "2*3 = 6 and a * b");
+        assert_eq!(strip_inline_markdown("**bold**, _em_ and `code` ~~gone~~"), "bold, em and code gone");
+        assert_eq!(strip_inline_markdown("see [the docs](https://example.com) for *my_var*"), "see the docs for my_var");
+    }
+}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+
+/// How `insert` derives the user-facing id of a document.
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdStrategy {
+    /// The caller's id, or the md5 of the text when none is given
+    #[default]
+    Provided,
+    Md5,
+    Sha256,
+    Uuid4,
+    /// sha256 of the text scoped by the value of a metadata field
+    Namespaced,
+}
+
+impl IdStrategy {
+    pub fn generate(&self, id: Option<&String>, text: &str, metadata: Option<&Map<String, Value>>, namespace_field: Option<&String>) -> String {
+        match self {
+            IdStrategy::Provided => match id {
+                Some(id) => id.clone(),
+                None => format!("{:x}", md5::compute(text)),
+            },
+            IdStrategy::Md5 => format!("{:x}", md5::compute(text)),
+            IdStrategy::Sha256 => format!("{:x}", Sha256::digest(text.as_bytes())),
+            IdStrategy::Uuid4 => Uuid::new_v4().to_string(),
+            IdStrategy::Namespaced => {
+                let namespace = namespace_field
+                    .and_then(|field| metadata.and_then(|m| m.get(field)))
+                    .map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                    .unwrap_or_default();
+
+                let mut hasher = Sha256::new();
+                hasher.update(namespace.as_bytes());
+                hasher.update([0u8]);
+                hasher.update(text.as_bytes());
+                format!("{:x}", hasher.finalize())
+            }
+        }
+    }
+}
+
+/// Hash of the text that gets embedded; the translation target is part of it
+/// because translating changes what the vector represents.
+pub fn content_hash(text: &str, translate_to: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    if let Some(translate_to) = translate_to {
+        hasher.update(translate_to.as_bytes());
+        hasher.update([0u8]);
+    }
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
@@ -0,0 +1,59 @@
+/// Cosine similarity of two vectors; 0 when either one is empty or all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Picks up to `count` candidates by maximal marginal relevance and returns their
+/// indexes in pick order. `lambda` weighs relevance against the similarity to the
+/// candidates already picked: 1 keeps the relevance order, 0 only spreads out.
+pub fn select(relevance: &[f32], vectors: &[Vec<f32>], count: usize, lambda: f32) -> Vec<usize> {
+    let mut picked: Vec<usize> = Vec::new();
+    let mut remaining: Vec<usize> = (0..relevance.len()).collect();
+    // Highest similarity of every candidate to anything picked so far
+    let mut redundancy: Vec<f32> = vec![0.0; relevance.len()];
+
+    while picked.len() < count && !remaining.is_empty() {
+        let (pos, &best) = remaining.iter()
+            .enumerate()
+            .max_by(|(_, &a), (_, &b)| {
+                let score_a = lambda * relevance[a] - (1.0 - lambda) * redundancy[a];
+                let score_b = lambda * relevance[b] - (1.0 - lambda) * redundancy[b];
+                // Ties go to the earlier, more relevant candidate
+                score_a.total_cmp(&score_b).then(b.cmp(&a))
+            })
+            .unwrap();
+        remaining.swap_remove(pos);
+        picked.push(best);
+
+        for &idx in &remaining {
+            let similarity = cosine_similarity(&vectors[idx], &vectors[best]);
+            redundancy[idx] = redundancy[idx].max(similarity);
+        }
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_at_the_lambda_extremes() {
+        let relevance = [0.9, 0.8, 0.1];
+        let vectors = vec![vec![1.0, 0.0], vec![1.0, 0.0], vec![0.0, 1.0]];
+
+        assert_eq!(select(&relevance, &vectors, 3, 1.0), vec![0, 1, 2]);
+        assert_eq!(select(&relevance, &vectors, 2, 0.0), vec![0, 2]);
+        assert_eq!(select(&relevance, &vectors, 5, 0.5), vec![0, 2, 1]);
+    }
+}
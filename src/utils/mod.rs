@@ -1 +1,8 @@
 pub mod ollama;
+pub mod ids;
+pub mod chunking;
+pub mod extract;
+pub mod cache;
+pub mod csv_mapping;
+pub mod bm25;
+pub mod mmr;
@@ -1,11 +1,35 @@
 use ollama_rs::generation::embeddings::request::{EmbeddingsInput, GenerateEmbeddingsRequest};
 use ollama_rs::generation::completion::request::GenerationRequest;
+use futures::{stream, Future, StreamExt};
+use crate::utils::cache::EmbeddingCache;
 
 
 pub struct Ollama {
 }
 
+pub const DEFAULT_PARALLELISM: usize = 4;
+pub const DEFAULT_EMBEDDING_BATCH_SIZE: usize = 32;
+
+/// Runs `f` over `inputs` with at most `parallelism` calls in flight, keeping input order.
+pub async fn bounded<T, R, F, Fut>(inputs: Vec<T>, parallelism: usize, f: F) -> Vec<R>
+where
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = R>,
+{
+    stream::iter(inputs).map(f).buffered(parallelism.max(1)).collect().await
+}
+
 impl Ollama {
+    pub fn get_parallelism() -> usize {
+        use std::env;
+        env::var("OLLAMA_PARALLELISM").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_PARALLELISM).max(1)
+    }
+
+    pub fn get_embedding_batch_size() -> usize {
+        use std::env;
+        env::var("OLLAMA_EMBEDDING_BATCH_SIZE").ok().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_EMBEDDING_BATCH_SIZE).max(1)
+    }
+
     pub async fn generate(prompt: String, model: String) -> Result<String, bool> {
         use ollama_rs::Ollama;
 
@@ -21,6 +45,11 @@ impl Ollama {
     pub async fn embedding(prompt: String, model: String) -> Result<Vec<f32>, bool> {
         use ollama_rs::Ollama;
 
+        let cache = EmbeddingCache::global();
+        if let Some(embeddings) = cache.and_then(|c| c.get(&model, &prompt)) {
+            return Ok(embeddings);
+        }
+
         let ollama = Ollama::default();
 
         let input: EmbeddingsInput = EmbeddingsInput::Single(prompt.clone());
@@ -30,8 +59,13 @@ impl Ollama {
 
         match res {
             Ok(r) => {
-                match r.embeddings.get(0) {
-                    Some(v) => Ok(v.clone()),
+                match r.embeddings.first() {
+                    Some(v) => {
+                        if let Some(cache) = cache {
+                            cache.put(&model, &prompt, v);
+                        }
+                        Ok(v.clone())
+                    },
                     None => Err(false)
                 }
             }
@@ -41,5 +75,53 @@ impl Ollama {
             },
         }
     }
+
+    /// Embeds several prompts in one request; the result is in prompt order.
+    /// Only prompts missing from the embedding cache are sent to Ollama.
+    pub async fn embeddings(prompts: Vec<String>, model: String) -> Result<Vec<Vec<f32>>, bool> {
+        use ollama_rs::Ollama;
+
+        let cache = EmbeddingCache::global();
+        let mut results: Vec<Option<Vec<f32>>> = prompts.iter()
+            .map(|prompt| cache.and_then(|c| c.get(&model, prompt)))
+            .collect();
+        let missing: Vec<usize> = (0..prompts.len()).filter(|&idx| results[idx].is_none()).collect();
+        if missing.is_empty() {
+            return Ok(results.into_iter().flatten().collect());
+        }
+
+        let ollama = Ollama::default();
+
+        let count = missing.len();
+        let input: EmbeddingsInput = EmbeddingsInput::Multiple(missing.iter().map(|&idx| prompts[idx].clone()).collect());
+
+        let request = GenerateEmbeddingsRequest::new(model.clone(), input);
+        let res = ollama.generate_embeddings(request).await;
+
+        match res {
+            Ok(r) if r.embeddings.len() == count => {
+                for (idx, embeddings) in missing.into_iter().zip(r.embeddings) {
+                    if let Some(cache) = cache {
+                        cache.put(&model, &prompts[idx], &embeddings);
+                    }
+                    results[idx] = Some(embeddings);
+                }
+                Ok(results.into_iter().flatten().collect())
+            },
+            Ok(r) => {
+                println!("model: {}, err: expected {} embeddings, got {}", model, count, r.embeddings.len());
+                Err(false)
+            }
+            Err(err) => {
+                println!("prompts: {}, model: {}, err: {}", count, model, err);
+                Err(false)
+            },
+        }
+    }
+
+    pub async fn dimension(model: String) -> Result<u64, bool> {
+        let embeddings = Ollama::embedding("dimension probe".to_string(), model).await?;
+        Ok(embeddings.len() as u64)
+    }
 }
  